@@ -1,5 +1,7 @@
 extern crate frug3d as frug;
 
 fn main() {
-    let _ = frug::run();
+    if let Err(err) = frug::run() {
+        eprintln!("{err}");
+    }
 }
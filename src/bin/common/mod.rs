@@ -0,0 +1,185 @@
+//! Command line options shared by the binaries.
+
+use std::sync::Arc;
+
+use frug::consts::{HEIGHT, WIDTH};
+use frug::prelude::*;
+
+/// Help text of the options understood by `RenderArgs`.
+pub const RENDER_OPTIONS_USAGE: &str = "\
+  --size <W>x<H>         Size in pixels [default: 1100x700]
+  --camera <X>,<Y>,<Z>   Camera position [default: framed to fit the meshes]
+  --yaw <DEG>            Camera yaw, positive turns towards +x [default: 30, or towards the
+                         meshes when --camera is given]
+  --pitch <DEG>          Camera pitch, positive looks up [default: -20, or towards the meshes
+                         when --camera is given]
+  --fov <DEG>            Vertical field of view [default: 60]
+  --ambient <INTENSITY>  Strength of the light reaching every side evenly [default: 0.05]
+  --projection <MODE>    perspective or orthographic [default: perspective]
+  --depth <MODE>         zbuffer or painter [default: zbuffer]
+  --interpolation <MODE> perspective or affine [default: perspective]
+  --shading <MODE>       flat, gouraud or phong [default: flat]";
+
+/// The meshes to show and how to look at them.
+pub struct RenderArgs {
+    pub obj_files: Vec<String>,
+    pub width: u32,
+    pub height: u32,
+    pub position: Option<Vec3>,
+    pub yaw: Option<f32>,
+    pub pitch: Option<f32>,
+    pub fov: f32,
+    pub ambient: f32,
+    pub projection: Projection,
+    pub settings: RenderSettings,
+}
+
+impl Default for RenderArgs {
+    fn default() -> Self {
+        RenderArgs {
+            obj_files: Vec::new(),
+            width: WIDTH,
+            height: HEIGHT,
+            position: None,
+            yaw: None,
+            pitch: None,
+            fov: FOV,
+            ambient: 0.05,
+            projection: Projection::Perspective,
+            settings: RenderSettings {
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Parse a number, naming the option it belongs to on error.
+fn parse_number(option: &str, value: &str) -> Result<f32, String> {
+    value
+        .parse::<f32>()
+        .map_err(|_| format!("{option}: `{value}` is not a number"))
+}
+
+/// Parse a `<W>x<H>` size.
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("--size: `{value}` is not a size like 800x600");
+
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    let width = width.parse::<u32>().map_err(|_| invalid())?;
+    let height = height.parse::<u32>().map_err(|_| invalid())?;
+    if width == 0 || height == 0 {
+        return Err(invalid());
+    }
+
+    Ok((width, height))
+}
+
+/// Parse a `<X>,<Y>,<Z>` position.
+fn parse_vec3(option: &str, value: &str) -> Result<Vec3, String> {
+    let coords = value
+        .split(',')
+        .map(|coord| parse_number(option, coord.trim()))
+        .collect::<Result<Vec<f32>, String>>()?;
+
+    match coords[..] {
+        [x, y, z] => Ok(Vec3 { x, y, z }),
+        _ => Err(format!("{option}: `{value}` is not a position like 0,1,-5")),
+    }
+}
+
+impl RenderArgs {
+    /// Apply `option` with its `value` when it is one of the shared options. Returns `Ok(false)`
+    /// for options it does not know, so the caller can handle its own.
+    pub fn parse_option(&mut self, option: &str, value: &str) -> Result<bool, String> {
+        match option {
+            "--size" => (self.width, self.height) = parse_size(value)?,
+            "--camera" => self.position = Some(parse_vec3(option, value)?),
+            "--yaw" => self.yaw = Some(parse_number(option, value)?.to_radians()),
+            "--pitch" => self.pitch = Some(parse_number(option, value)?.to_radians()),
+            "--fov" => self.fov = parse_number(option, value)?.to_radians(),
+            "--ambient" => self.ambient = parse_number(option, value)?,
+            "--projection" => {
+                self.projection = match value {
+                    "perspective" => Projection::Perspective,
+                    "orthographic" => Projection::Orthographic,
+                    _ => return Err(format!("{option}: unknown projection `{value}`")),
+                }
+            }
+            "--depth" => {
+                self.settings.depth_mode = match value {
+                    "zbuffer" => DepthMode::ZBuffer,
+                    "painter" => DepthMode::Painter,
+                    _ => return Err(format!("{option}: unknown depth mode `{value}`")),
+                }
+            }
+            "--interpolation" => {
+                self.settings.interpolation = match value {
+                    "perspective" => Interpolation::PerspectiveCorrect,
+                    "affine" => Interpolation::Affine,
+                    _ => return Err(format!("{option}: unknown interpolation `{value}`")),
+                }
+            }
+            "--shading" => {
+                self.settings.shading = match value {
+                    "flat" => Shading::Flat,
+                    "gouraud" => Shading::Gouraud,
+                    "phong" => Shading::Phong,
+                    _ => return Err(format!("{option}: unknown shading `{value}`")),
+                }
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Load every OBJ file into a scene, in the same world space, lit by the sun and the sky.
+    pub fn load_scene(&self) -> Result<Scene, MeshLoadError> {
+        let mut scene = Scene::default();
+        for obj_file in self.obj_files.iter() {
+            scene.add(Arc::new(load_obj_file_data(obj_file.clone())?));
+        }
+
+        // The same lights as the demo scene: the sun shining down and towards +z, a bluish sky
+        // and a brownish ground
+        scene.lights.push(Light::directional(Vec3 {
+            x: 0.0,
+            y: -1.0,
+            z: 1.0,
+        }));
+        scene.lights.push(Light {
+            intensity: 0.35,
+            ..Light::hemisphere([0x90, 0xa8, 0xc8, 0xff], [0x40, 0x38, 0x30, 0xff])
+        });
+        scene.lights.push(Light::ambient(self.ambient));
+
+        Ok(scene)
+    }
+
+    /// Place the camera as asked, framing the scene with whatever was left out.
+    pub fn camera(&self, scene: &Scene) -> Camera {
+        let mut camera = Camera {
+            fov: self.fov,
+            projection: self.projection,
+            yaw: self.yaw.unwrap_or(30f32.to_radians()),
+            pitch: self.pitch.unwrap_or(-20f32.to_radians()),
+            ..Default::default()
+        };
+
+        let Some(bounds) = scene.bounding_box() else {
+            return camera;
+        };
+
+        let aspect_ratio = self.width as f32 / self.height as f32;
+        camera.frame(&bounds, aspect_ratio);
+
+        if let Some(position) = self.position {
+            camera.position = position;
+            if self.yaw.is_none() && self.pitch.is_none() {
+                camera.look_at(&bounds.center());
+            }
+        }
+
+        camera
+    }
+}
@@ -0,0 +1,219 @@
+//! Render OBJ files to image files, without opening a window.
+//!
+//! ```text
+//! render [OPTIONS] <OBJ>...
+//! ```
+//!
+//! Run with `--help` for the list of options.
+extern crate frug3d as frug;
+
+mod common;
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use common::{RenderArgs, RENDER_OPTIONS_USAGE};
+use frug::prelude::*;
+
+fn usage() -> String {
+    format!(
+        "\
+Usage: render [OPTIONS] <OBJ>...
+
+Renders every OBJ file given, in the same world space, into an image, or into a numbered
+sequence of images of a full turn with --turntable.
+
+Options:
+  -o, --output <FILE>    Image to write, as PNG or PPM depending on the extension. Can be
+                         given more than once [default: <OBJ>.png and <OBJ>.ppm, named after
+                         the first OBJ file]
+  --turntable <FRAMES>   Record a 360 degree turn as frame_0001.ppm, frame_0002.ppm... instead
+  --spin <WHAT>          What turns: model or camera [default: model]
+  --frames-dir <DIR>     Directory the turntable frames are written to [default: .]
+{RENDER_OPTIONS_USAGE}
+  -h, --help             Print this help"
+    )
+}
+
+/// Command line arguments of the render binary.
+struct Args {
+    render: RenderArgs,
+    outputs: Vec<String>,
+    turntable: Option<Turntable>,
+    spin: TurntableSpin,
+    frames_dir: String,
+}
+
+/// Parse the command line arguments (without the program name). Returns `Ok(None)` when the
+/// help was asked for.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        render: RenderArgs {
+            ..Default::default()
+        },
+        outputs: Vec::new(),
+        turntable: None,
+        spin: TurntableSpin::Model,
+        frames_dir: ".".to_string(),
+    };
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(None);
+        }
+        if arg != "-o" && !arg.starts_with("--") {
+            parsed.render.obj_files.push(arg);
+            continue;
+        }
+
+        let value = args.next().ok_or_else(|| format!("{arg}: missing value"))?;
+        match arg.as_str() {
+            "-o" | "--output" => parsed.outputs.push(value),
+            "--turntable" => {
+                let frames = value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|&frames| frames > 0)
+                    .ok_or_else(|| format!("{arg}: `{value}` is not a number of frames"))?;
+                parsed.turntable = Some(Turntable {
+                    frames,
+                    ..Default::default()
+                });
+            }
+            "--spin" => {
+                parsed.spin = match value.as_str() {
+                    "model" => TurntableSpin::Model,
+                    "camera" => TurntableSpin::Camera,
+                    _ => return Err(format!("{arg}: unknown spin `{value}`")),
+                }
+            }
+            "--frames-dir" => parsed.frames_dir = value,
+            _ => {
+                if !parsed.render.parse_option(&arg, &value)? {
+                    return Err(format!("unknown option `{arg}`"));
+                }
+            }
+        }
+    }
+
+    let Some(first_obj) = parsed.render.obj_files.first() else {
+        return Err("no OBJ file given".to_string());
+    };
+
+    if let Some(turntable) = parsed.turntable.as_mut() {
+        if !parsed.outputs.is_empty() {
+            return Err("--output cannot be used with --turntable".to_string());
+        }
+        turntable.spin = parsed.spin;
+        return Ok(Some(parsed));
+    }
+
+    // Name the images after the first OBJ file by default
+    if parsed.outputs.is_empty() {
+        let stem = Path::new(first_obj)
+            .file_stem()
+            .map_or("render".into(), |stem| stem.to_string_lossy());
+        parsed.outputs = vec![format!("{stem}.png"), format!("{stem}.ppm")];
+    }
+
+    // Check every output now, rather than after rendering
+    for output in parsed.outputs.iter() {
+        if image_format(output).is_none() {
+            return Err(format!("{output}: the image must end in .png or .ppm"));
+        }
+    }
+
+    Ok(Some(parsed))
+}
+
+/// Image formats the renderer can write.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+/// The image format matching the extension of `filename`.
+fn image_format(filename: &str) -> Option<ImageFormat> {
+    let extension = Path::new(filename).extension()?.to_str()?;
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => Some(ImageFormat::Png),
+        "ppm" => Some(ImageFormat::Ppm),
+        _ => None,
+    }
+}
+
+fn main() -> ExitCode {
+    // Show the warnings of the loaders, such as a missing material library
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", usage());
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\nRun with --help for the list of options.");
+            return ExitCode::from(2);
+        }
+    };
+
+    let scene = match args.render.load_scene() {
+        Ok(scene) => scene,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let camera = args.render.camera(&scene);
+
+    let (width, height) = (args.render.width, args.render.height);
+    let mut renderer = match HeadlessRenderer::new(width, height) {
+        Ok(renderer) => renderer,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    renderer.settings = args.render.settings;
+
+    if let Some(turntable) = args.turntable {
+        if let Err(err) = std::fs::create_dir_all(&args.frames_dir).and_then(|_| {
+            record_turntable(
+                &mut renderer,
+                &scene,
+                &camera,
+                &turntable,
+                args.frames_dir.clone(),
+            )
+        }) {
+            eprintln!("error: could not record the turntable: {err}");
+            return ExitCode::FAILURE;
+        }
+
+        return ExitCode::SUCCESS;
+    }
+
+    let pixels = renderer.render_scene(&scene, &camera);
+
+    for output in args.outputs.iter() {
+        let result = match image_format(output) {
+            Some(ImageFormat::Png) => {
+                save_png(output.clone(), width, height, &pixels).map_err(|err| err.to_string())
+            }
+            Some(ImageFormat::Ppm) => {
+                save_ppm(output.clone(), width, height, &pixels).map_err(|err| err.to_string())
+            }
+            None => unreachable!("outputs are checked when parsing the arguments"),
+        };
+
+        if let Err(err) = result {
+            eprintln!("error: could not write {output}: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,133 @@
+//! Interactive viewer for OBJ files.
+//!
+//! ```text
+//! viewer [OPTIONS] <OBJ>...
+//! ```
+//!
+//! Run with `--help` for the list of options.
+extern crate frug3d as frug;
+
+mod common;
+
+use std::process::ExitCode;
+
+use common::{RenderArgs, RENDER_OPTIONS_USAGE};
+use frug::{run_viewer, FramePacing, ViewerOptions};
+
+fn usage() -> String {
+    format!(
+        "\
+Usage: viewer [OPTIONS] <OBJ>...
+
+Opens a window showing every OBJ file given, in the same world space.
+
+Options:
+{RENDER_OPTIONS_USAGE}
+  --fps <FPS>            Frames per second, vsync or unlimited [default: 60]
+  -h, --help             Print this help
+
+Controls:
+  C          switch between the orbit and the fly camera
+  Drag       orbit (or look around in fly mode), scroll to zoom
+  WASD, E/Q  move the fly camera
+  1, 2, 3    front, side and top views
+  P          toggle the orthographic projection
+  Z, I       toggle the painter's algorithm and affine interpolation
+  G          cycle flat, Gouraud and per-pixel Phong shading
+  F12        save a screenshot (shift+F12 to save the depth buffer too)
+  Esc        quit"
+    )
+}
+
+/// Command line arguments of the viewer.
+struct Args {
+    render: RenderArgs,
+    pacing: FramePacing,
+}
+
+/// Parse the command line arguments (without the program name). Returns `Ok(None)` when the
+/// help was asked for.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut parsed = Args {
+        render: RenderArgs {
+            ..Default::default()
+        },
+        pacing: FramePacing::default(),
+    };
+
+    while let Some(arg) = args.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(None);
+        }
+        if !arg.starts_with("--") {
+            parsed.render.obj_files.push(arg);
+            continue;
+        }
+
+        let value = args.next().ok_or_else(|| format!("{arg}: missing value"))?;
+        if arg == "--fps" {
+            parsed.pacing = match value.as_str() {
+                "vsync" => FramePacing::VSync,
+                "unlimited" => FramePacing::Unlimited,
+                _ => value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|&fps| fps > 0)
+                    .map(FramePacing::TargetFps)
+                    .ok_or_else(|| format!("{arg}: `{value}` is not a frame rate"))?,
+            };
+        } else if !parsed.render.parse_option(&arg, &value)? {
+            return Err(format!("unknown option `{arg}`"));
+        }
+    }
+
+    if parsed.render.obj_files.is_empty() {
+        return Err("no OBJ file given".to_string());
+    }
+
+    Ok(Some(parsed))
+}
+
+fn main() -> ExitCode {
+    // Show the warnings of the loaders, such as a missing material library, and where
+    // screenshots are saved
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn,frug3d=info"))
+        .init();
+
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", usage());
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\nRun with --help for the list of options.");
+            return ExitCode::from(2);
+        }
+    };
+
+    let scene = match args.render.load_scene() {
+        Ok(scene) => scene,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let camera = args.render.camera(&scene);
+    let options = ViewerOptions {
+        title: format!("frug3d - {}", args.render.obj_files.join(", ")),
+        width: args.render.width,
+        height: args.render.height,
+        settings: args.render.settings,
+        pacing: args.pacing,
+        ..Default::default()
+    };
+
+    if let Err(err) = run_viewer(scene, camera, options) {
+        eprintln!("error: {err}");
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
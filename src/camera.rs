@@ -0,0 +1,140 @@
+use std::f32::consts::FRAC_PI_2;
+
+use crate::consts::{FOV, ZFAR, ZNEAR};
+use crate::matrix::{mat4_look_at, mat4_make_orthographic, mat4_make_perspective, Mat4};
+use crate::mesh::BoundingBox;
+use crate::vector::{vec3_add, vec3_length, vec3_mul, vec3_sub, Vec3};
+
+/// How the camera projects the view onto the screen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Projection {
+    /// Distant things look smaller, as seen by an eye.
+    #[default]
+    Perspective,
+    /// Parallel projection that keeps sizes regardless of distance, for technical views.
+    Orthographic,
+}
+
+/// Standard views that look at a model along one of the world axes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AxisView {
+    /// Looking down +z.
+    Front,
+    /// Looking down -x, from the right of the model.
+    Side,
+    /// Looking down -y, with +z pointing up on screen.
+    Top,
+}
+
+/// A camera placed in the world, looking along the direction given by its yaw and pitch angles.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub position: Vec3,
+    /// Rotation around the y axis, in radians. 0 looks down +z and positive angles turn to +x.
+    pub yaw: f32,
+    /// Rotation above the horizon, in radians. Positive angles look up.
+    pub pitch: f32,
+    /// Vertical field of view, in radians.
+    pub fov: f32,
+    pub znear: f32,
+    pub zfar: f32,
+    pub projection: Projection,
+    /// Height of the view volume in world units, for the orthographic projection.
+    pub ortho_height: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            position: Vec3 {
+                ..Default::default()
+            },
+            yaw: 0.0,
+            pitch: 0.0,
+            fov: FOV,
+            znear: ZNEAR,
+            zfar: ZFAR,
+            projection: Projection::Perspective,
+            ortho_height: 5.0,
+        }
+    }
+}
+
+impl Camera {
+    /// The unit vector the camera is looking along.
+    pub fn direction(&self) -> Vec3 {
+        Vec3 {
+            x: self.pitch.cos() * self.yaw.sin(),
+            y: self.pitch.sin(),
+            z: self.pitch.cos() * self.yaw.cos(),
+        }
+    }
+
+    /// The unit vector pointing to the top of the screen, perpendicular to the direction.
+    pub fn up(&self) -> Vec3 {
+        Vec3 {
+            x: -self.pitch.sin() * self.yaw.sin(),
+            y: self.pitch.cos(),
+            z: -self.pitch.sin() * self.yaw.cos(),
+        }
+    }
+
+    /// Turn the camera so that it looks at `target`.
+    pub fn look_at(&mut self, target: &Vec3) {
+        let direction = vec3_sub(target, &self.position);
+        let length = vec3_length(direction);
+        if length == 0.0 {
+            return;
+        }
+
+        self.yaw = direction.x.atan2(direction.z);
+        self.pitch = (direction.y / length).asin();
+    }
+
+    /// Matrix that moves world space into view space, where the camera sits at the origin and
+    /// looks down +z.
+    pub fn view_matrix(&self) -> Mat4 {
+        // Using the camera's own up vector keeps the matrix valid when looking straight down
+        let target = vec3_add(&self.position, &self.direction());
+
+        mat4_look_at(&self.position, &target, &self.up())
+    }
+
+    /// Projection of the camera for a viewport of the given aspect ratio (width over height).
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Mat4 {
+        match self.projection {
+            Projection::Perspective => {
+                mat4_make_perspective(self.fov, 1.0 / aspect_ratio, self.znear, self.zfar)
+            }
+            Projection::Orthographic => {
+                mat4_make_orthographic(self.ortho_height, 1.0 / aspect_ratio, self.znear, self.zfar)
+            }
+        }
+    }
+
+    /// Place the camera so that the whole of `bounds` fills the view of the given aspect ratio
+    /// (width over height), looking along the current direction. Both projections are framed, so
+    /// switching between them keeps the model in view.
+    pub fn frame(&mut self, bounds: &BoundingBox, aspect_ratio: f32) {
+        let radius = bounds.radius();
+
+        // Fit the bounding sphere into the narrowest of the two fields of view
+        let half_fov_y = self.fov / 2.0;
+        let half_fov_x = (half_fov_y.tan() * aspect_ratio).atan();
+        let distance = radius / half_fov_x.min(half_fov_y).sin();
+
+        self.ortho_height = 2.0 * radius * (1.0 / aspect_ratio).max(1.0);
+        self.position = vec3_sub(&bounds.center(), &vec3_mul(&self.direction(), distance));
+    }
+
+    /// Look at `bounds` along one of the world axes, framing it in a view of the given aspect
+    /// ratio (width over height).
+    pub fn frame_axis_view(&mut self, view: AxisView, bounds: &BoundingBox, aspect_ratio: f32) {
+        (self.yaw, self.pitch) = match view {
+            AxisView::Front => (0.0, 0.0),
+            AxisView::Side => (-FRAC_PI_2, 0.0),
+            AxisView::Top => (0.0, -FRAC_PI_2),
+        };
+        self.frame(bounds, aspect_ratio);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::texture::Tex2;
+use crate::vector::{Vec3, Vec4};
+
+/// The sides of the frustum are clipped against a guard band this many times wider than the
+/// view. Triangles poking out of the screen a little are left to the rasterizer, which only
+/// visits pixels inside the viewport; only huge triangles get cut, so that their screen
+/// coordinates stay within the range the rasterizer handles.
+pub const GUARD_BAND_SCALE: f32 = 16.0;
+
+/// A vertex in homogeneous clip space, with the attributes that get interpolated when a
+/// triangle is cut by a clipping plane.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct ClipVertex {
+    pub position: Vec4,
+    pub texcoord: Tex2,
+    /// Lit color of the vertex, for smooth shading.
+    pub rgba: [f32; 4],
+    /// World-space position and normal of the vertex, for per-pixel lighting.
+    pub world_position: Vec3,
+    pub normal: Vec3,
+}
+
+/// The six planes of the view frustum, in homogeneous clip space. A point is visible when
+/// `-w <= x <= w`, `-w <= y <= w` and `0 <= z <= w`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FrustumPlane {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Near,
+    Far,
+}
+
+pub const FRUSTUM_PLANES: [FrustumPlane; 6] = [
+    FrustumPlane::Near,
+    FrustumPlane::Far,
+    FrustumPlane::Left,
+    FrustumPlane::Right,
+    FrustumPlane::Top,
+    FrustumPlane::Bottom,
+];
+
+/// Signed distance of a clip-space point to a plane, scaled by `side_scale` for the sides of the
+/// frustum. Positive (or 0) means the point is on the inside.
+fn plane_distance(plane: FrustumPlane, p: &Vec4, side_scale: f32) -> f32 {
+    match plane {
+        FrustumPlane::Left => p.x + side_scale * p.w,
+        FrustumPlane::Right => side_scale * p.w - p.x,
+        FrustumPlane::Bottom => p.y + side_scale * p.w,
+        FrustumPlane::Top => side_scale * p.w - p.y,
+        FrustumPlane::Near => p.z,
+        FrustumPlane::Far => p.w - p.z,
+    }
+}
+
+/// Linearly interpolate between two clip-space vertices and all of their attributes.
+pub fn clip_vertex_lerp(a: &ClipVertex, b: &ClipVertex, t: f32) -> ClipVertex {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+
+    ClipVertex {
+        position: Vec4 {
+            x: lerp(a.position.x, b.position.x),
+            y: lerp(a.position.y, b.position.y),
+            z: lerp(a.position.z, b.position.z),
+            w: lerp(a.position.w, b.position.w),
+        },
+        texcoord: Tex2 {
+            u: lerp(a.texcoord.u, b.texcoord.u),
+            v: lerp(a.texcoord.v, b.texcoord.v),
+        },
+        rgba: [0, 1, 2, 3].map(|i| lerp(a.rgba[i], b.rgba[i])),
+        world_position: Vec3 {
+            x: lerp(a.world_position.x, b.world_position.x),
+            y: lerp(a.world_position.y, b.world_position.y),
+            z: lerp(a.world_position.z, b.world_position.z),
+        },
+        normal: Vec3 {
+            x: lerp(a.normal.x, b.normal.x),
+            y: lerp(a.normal.y, b.normal.y),
+            z: lerp(a.normal.z, b.normal.z),
+        },
+    }
+}
+
+/// Whether the whole triangle lies outside one of the frustum planes, so it can be discarded
+/// without clipping.
+pub fn triangle_outside_frustum(vertices: &[ClipVertex; 3]) -> bool {
+    FRUSTUM_PLANES.iter().any(|&plane| {
+        vertices
+            .iter()
+            .all(|vertex| plane_distance(plane, &vertex.position, 1.0) < 0.0)
+    })
+}
+
+/// Cut a convex polygon by a single plane (one step of Sutherland-Hodgman), keeping the part on
+/// the inside.
+fn clip_polygon_against_plane(
+    polygon: &[ClipVertex],
+    plane: FrustumPlane,
+    side_scale: f32,
+) -> Vec<ClipVertex> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+
+    for (i, current) in polygon.iter().enumerate() {
+        let previous = &polygon[(i + polygon.len() - 1) % polygon.len()];
+        let current_distance = plane_distance(plane, &current.position, side_scale);
+        let previous_distance = plane_distance(plane, &previous.position, side_scale);
+
+        // The edge crosses the plane: add the intersection point
+        if (current_distance >= 0.0) != (previous_distance >= 0.0) {
+            let t = previous_distance / (previous_distance - current_distance);
+            clipped.push(clip_vertex_lerp(previous, current, t));
+        }
+
+        if current_distance >= 0.0 {
+            clipped.push(*current);
+        }
+    }
+
+    clipped
+}
+
+/// Clip a triangle in homogeneous clip space, before the perspective divide. The near and far
+/// planes are clipped exactly and the sides against the guard band. Returns a convex polygon
+/// (to be triangulated as a fan), empty when nothing of the triangle is visible.
+pub fn clip_triangle(vertices: &[ClipVertex; 3]) -> Vec<ClipVertex> {
+    if triangle_outside_frustum(vertices) {
+        return Vec::new();
+    }
+
+    let mut polygon = vertices.to_vec();
+    for plane in FRUSTUM_PLANES {
+        let side_scale = match plane {
+            FrustumPlane::Near | FrustumPlane::Far => 1.0,
+            _ => GUARD_BAND_SCALE,
+        };
+        polygon = clip_polygon_against_plane(&polygon, plane, side_scale);
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+    }
+
+    polygon
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(x: f32, y: f32, z: f32, u: f32) -> ClipVertex {
+        ClipVertex {
+            position: Vec4 { x, y, z, w: 1.0 },
+            texcoord: Tex2 { u, v: 0.0 },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn visible_triangles_are_left_alone() {
+        let triangle = [
+            vertex(-0.5, -0.5, 0.2, 0.0),
+            vertex(0.5, -0.5, 0.4, 0.0),
+            vertex(0.0, 0.5, 0.6, 0.0),
+        ];
+
+        assert_eq!(clip_triangle(&triangle), triangle.to_vec());
+    }
+
+    #[test]
+    fn triangles_behind_the_near_or_past_the_far_plane_are_dropped() {
+        let behind = [
+            vertex(-0.5, -0.5, -0.2, 0.0),
+            vertex(0.5, -0.5, -0.4, 0.0),
+            vertex(0.0, 0.5, -0.1, 0.0),
+        ];
+        let beyond = [
+            vertex(-0.5, -0.5, 1.2, 0.0),
+            vertex(0.5, -0.5, 1.4, 0.0),
+            vertex(0.0, 0.5, 1.1, 0.0),
+        ];
+
+        assert!(clip_triangle(&behind).is_empty());
+        assert!(clip_triangle(&beyond).is_empty());
+    }
+
+    #[test]
+    fn the_near_plane_cuts_triangles_and_their_attributes() {
+        // The texture coordinate follows z, so it must be 0.0 wherever the plane cuts
+        let triangle = [
+            vertex(-0.5, -0.5, -0.5, -0.5),
+            vertex(0.5, -0.5, 0.5, 0.5),
+            vertex(0.0, 0.5, 0.5, 0.5),
+        ];
+
+        let polygon = clip_triangle(&triangle);
+        assert_eq!(polygon.len(), 4);
+        for vertex in polygon.iter() {
+            assert!(vertex.position.z >= 0.0);
+            assert!((vertex.texcoord.u - vertex.position.z).abs() < 1e-6);
+        }
+        assert_eq!(
+            polygon
+                .iter()
+                .filter(|vertex| vertex.position.z == 0.0)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn the_sides_are_clipped_against_the_guard_band() {
+        // Poking out of the view a little is left to the rasterizer
+        let wide = [
+            vertex(-3.0, -0.5, 0.5, 0.0),
+            vertex(3.0, -0.5, 0.5, 0.0),
+            vertex(0.0, 2.0, 0.5, 0.0),
+        ];
+        assert_eq!(clip_triangle(&wide), wide.to_vec());
+
+        // Huge triangles get cut down to the guard band
+        let huge = [
+            vertex(-100.0, -0.5, 0.5, 0.0),
+            vertex(100.0, -0.5, 0.5, 0.0),
+            vertex(0.0, 0.5, 0.5, 0.0),
+        ];
+        let polygon = clip_triangle(&huge);
+        assert!(polygon.len() >= 3);
+        for vertex in polygon.iter() {
+            assert!(vertex.position.x.abs() <= GUARD_BAND_SCALE * vertex.position.w + 1e-4);
+        }
+    }
+}
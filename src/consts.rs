@@ -16,3 +16,6 @@ pub const HEIGHT: u32 = 700;
 pub const BACKGROUND_COLOR: [u8; 4] = C_BLACK;
 pub const FPS: u32 = 60;
 pub const FRAME_TARGET_TIME: f32 = 1000.0 / FPS as f32;
+pub const FOV: f32 = std::f32::consts::PI / 3.0; // 180 / 3 = 60 degrees.
+pub const ZNEAR: f32 = 0.1;
+pub const ZFAR: f32 = 100.0;
@@ -0,0 +1,225 @@
+use std::f32::consts::FRAC_PI_2;
+
+use winit::event::VirtualKeyCode;
+use winit_input_helper::WinitInputHelper;
+
+use crate::camera::Camera;
+use crate::mesh::BoundingBox;
+use crate::vector::{vec3_add, vec3_cross, vec3_length, vec3_mul, vec3_normalize, vec3_sub, Vec3};
+
+// ===================================================================
+// Variables & definitions
+// ===================================================================
+
+/// Keep the pitch just short of straight up or down, where the yaw stops making sense.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// Which controller drives the camera.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum CameraMode {
+    /// Move freely with WASD and look around by dragging the mouse.
+    Fly,
+    /// Drag the mouse to orbit around a target and scroll to zoom.
+    #[default]
+    Orbit,
+}
+
+/// First-person controller: W/S move forward and back, A/D strafe, E/Q move up and down, and
+/// dragging with the left mouse button turns the camera. Holding shift moves faster.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlyController {
+    /// Movement speed, in world units per second.
+    pub move_speed: f32,
+    /// Rotation per pixel of mouse movement, in radians.
+    pub look_sensitivity: f32,
+}
+
+impl Default for FlyController {
+    fn default() -> Self {
+        FlyController {
+            move_speed: 2.0,
+            look_sensitivity: 0.005,
+        }
+    }
+}
+
+/// Controller that keeps the camera looking at `target` from `distance` away: dragging with the
+/// left mouse button orbits around it and the scroll wheel zooms in and out, for both
+/// projections.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    /// Angle around the y axis of the camera position, seen from the target, in radians.
+    pub yaw: f32,
+    /// Angle above the horizon of the camera position, seen from the target, in radians.
+    pub pitch: f32,
+    /// Rotation per pixel of mouse movement, in radians.
+    pub rotate_sensitivity: f32,
+    /// Fraction of the distance covered by each step of the scroll wheel.
+    pub zoom_step: f32,
+    /// Closest the camera can get to the target.
+    pub min_distance: f32,
+}
+
+impl Default for OrbitController {
+    fn default() -> Self {
+        OrbitController {
+            target: Vec3 {
+                ..Default::default()
+            },
+            distance: 5.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            rotate_sensitivity: 0.01,
+            zoom_step: 0.1,
+            min_distance: 0.1,
+        }
+    }
+}
+
+// ===================================================================
+// Functions
+// ===================================================================
+
+impl FlyController {
+    /// Move and turn the camera from the current input. `dt` is the real time since the last
+    /// update, in seconds.
+    pub fn update(&self, camera: &mut Camera, input: &WinitInputHelper, dt: f32) {
+        // Mouse look
+        if input.mouse_held(0) {
+            let (dx, dy) = input.mouse_diff();
+            camera.yaw += dx * self.look_sensitivity;
+            camera.pitch = (camera.pitch - dy * self.look_sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        // Movement, relative to where the camera is looking
+        let forward = camera.direction();
+        let up = Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+        let mut right = vec3_cross(&up, &forward);
+        vec3_normalize(&mut right);
+
+        let axis = |positive: VirtualKeyCode, negative: VirtualKeyCode| -> f32 {
+            input.key_held(positive) as i32 as f32 - input.key_held(negative) as i32 as f32
+        };
+
+        let mut movement = vec3_mul(&forward, axis(VirtualKeyCode::W, VirtualKeyCode::S));
+        movement = vec3_add(
+            &movement,
+            &vec3_mul(&right, axis(VirtualKeyCode::D, VirtualKeyCode::A)),
+        );
+        movement = vec3_add(
+            &movement,
+            &vec3_mul(&up, axis(VirtualKeyCode::E, VirtualKeyCode::Q)),
+        );
+        if vec3_length(movement) == 0.0 {
+            return;
+        }
+        vec3_normalize(&mut movement);
+
+        let mut speed = self.move_speed;
+        if input.held_shift() {
+            speed *= 4.0;
+        }
+
+        camera.position = vec3_add(&camera.position, &vec3_mul(&movement, speed * dt));
+    }
+}
+
+impl OrbitController {
+    /// Orbit controller looking at the center of `bounds` from `camera`'s current position.
+    pub fn framing(bounds: &BoundingBox, camera: &Camera) -> Self {
+        let mut orbit = OrbitController {
+            target: bounds.center(),
+            min_distance: bounds.radius() * 0.1,
+            ..Default::default()
+        };
+
+        let offset = vec3_sub(&camera.position, &orbit.target);
+        let distance = vec3_length(offset);
+        if distance > 0.0 {
+            orbit.distance = distance;
+            // Straight above or below the target, the offset has no usable heading: keep the one
+            // the camera is facing so the view is not flipped upside down
+            orbit.yaw = if offset.x.hypot(offset.z) > distance * MAX_PITCH.cos() {
+                offset.x.atan2(offset.z)
+            } else {
+                camera.yaw + std::f32::consts::PI
+            };
+            orbit.pitch = (offset.y / distance).asin().clamp(-MAX_PITCH, MAX_PITCH);
+        } else {
+            // Camera sitting on the target: back away along -z far enough to see everything
+            orbit.distance = bounds.radius() / (camera.fov / 2.0).sin();
+            orbit.yaw = std::f32::consts::PI;
+        }
+
+        orbit
+    }
+
+    /// Orbit and zoom from the current input and place the camera accordingly. Mouse and scroll
+    /// deltas already cover the whole frame, so `dt` is unused for now.
+    pub fn update(&mut self, camera: &mut Camera, input: &WinitInputHelper, _dt: f32) {
+        if input.mouse_held(0) {
+            let (dx, dy) = input.mouse_diff();
+            self.yaw -= dx * self.rotate_sensitivity;
+            self.pitch = (self.pitch + dy * self.rotate_sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+
+        let scroll = input.scroll_diff();
+        if scroll != 0.0 {
+            let zoom = (1.0 - self.zoom_step).powf(scroll);
+            let distance = (self.distance * zoom).max(self.min_distance);
+            camera.ortho_height *= distance / self.distance;
+            self.distance = distance;
+        }
+
+        self.apply(camera);
+    }
+
+    /// Put the camera on the orbit and point it at the target.
+    pub fn apply(&self, camera: &mut Camera) {
+        let offset = Vec3 {
+            x: self.pitch.cos() * self.yaw.sin(),
+            y: self.pitch.sin(),
+            z: self.pitch.cos() * self.yaw.cos(),
+        };
+        camera.position = vec3_add(&self.target, &vec3_mul(&offset, self.distance));
+        camera.look_at(&self.target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::AxisView;
+
+    #[test]
+    fn orbiting_from_the_top_view_keeps_it_upright() {
+        let bounds = BoundingBox {
+            min: Vec3 {
+                x: -1.0,
+                y: -1.0,
+                z: -1.0,
+            },
+            max: Vec3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+        };
+        let mut camera = Camera {
+            ..Default::default()
+        };
+        camera.frame_axis_view(AxisView::Top, &bounds, 1.0);
+        assert!(camera.up().z > 0.0);
+
+        let mut orbit = OrbitController::framing(&bounds, &camera);
+        orbit.update(&mut camera, &WinitInputHelper::new(), 1.0 / 60.0);
+        assert!(camera.up().z > 0.0);
+        assert!(camera.direction().y < -0.99);
+    }
+}
@@ -1,14 +1,16 @@
 use crate::{
-    consts::{HEIGHT, WIDTH},
-    helpers::sort_vertices,
+    framebuffer::Framebuffer,
+    light::color_multiply,
+    texture::{texture_sample, Tex2, Texture},
+    triangle::{interpolate_attribute, interpolate_color, Interpolation},
+    vector::{Vec3, Vec4},
 };
 
-pub fn draw_pixel(frame: &mut [u8], xpos: usize, ypos: usize, rgba: [u8; 4]) {
-    if xpos < WIDTH as usize && ypos < HEIGHT as usize {
-        frame[(WIDTH as usize * ypos * 4) + (xpos * 4)] = rgba[0];
-        frame[(WIDTH as usize * ypos * 4) + (xpos * 4) + 1] = rgba[1];
-        frame[(WIDTH as usize * ypos * 4) + (xpos * 4) + 2] = rgba[2];
-        frame[(WIDTH as usize * ypos * 4) + (xpos * 4) + 3] = rgba[3];
+pub fn draw_pixel(framebuffer: &mut Framebuffer, xpos: usize, ypos: usize, rgba: [u8; 4]) {
+    let width = framebuffer.width as usize;
+    if xpos < width && ypos < framebuffer.height as usize {
+        let index = (width * ypos * 4) + (xpos * 4);
+        framebuffer.color_buffer[index..index + 4].copy_from_slice(&rgba);
     }
 }
 
@@ -18,8 +20,13 @@ pub fn clear_color_buffer(frame: &mut [u8], rgba: [u8; 4]) {
     }
 }
 
-/// Draws a line from coordinate 0 to coordinate 1 given a color and a frame.
-pub fn draw_line(frame: &mut [u8], x0: i32, y0: i32, x1: i32, y1: i32, rgba: [u8; 4]) {
+/// Reset every depth value to 1.0, the depth of the far plane.
+pub fn clear_depth_buffer(depth_buffer: &mut [f32]) {
+    depth_buffer.fill(1.0);
+}
+
+/// Draws a line from coordinate 0 to coordinate 1 given a color and a framebuffer.
+pub fn draw_line(framebuffer: &mut Framebuffer, x0: i32, y0: i32, x1: i32, y1: i32, rgba: [u8; 4]) {
     let delta_x = x1 - x0;
     let delta_y = y1 - y0;
 
@@ -38,9 +45,9 @@ pub fn draw_line(frame: &mut [u8], x0: i32, y0: i32, x1: i32, y1: i32, rgba: [u8
     let mut current_y = y0 as f32;
 
     // loop each step and draw the pixel
-    for i in 0..side_length {
+    for _ in 0..side_length {
         draw_pixel(
-            frame,
+            framebuffer,
             current_x.round() as usize,
             current_y.round() as usize,
             rgba,
@@ -50,98 +57,227 @@ pub fn draw_line(frame: &mut [u8], x0: i32, y0: i32, x1: i32, y1: i32, rgba: [u8
     }
 }
 
-fn fill_flat_bottom_triangle(
-    frame: &mut [u8],
-    rgba: [u8; 4],
-    x0: i32,
-    y0: i32,
-    x1: i32,
-    y1: i32,
-    xm: i32,
-    ym: i32,
-) {
-    // Find the 2 slopes (two triangle legs)
-    //  slope1 = c0 -> c1
-    //  slope2 = c0 -> cm
-    let inverse_slope1: f32 = (x1 - x0) as f32 / (y1 - y0) as f32;
-    let inverse_slope2: f32 = (xm - x0) as f32 / (ym - y0) as f32;
+/// Number of fractional bits of the fixed-point vertex positions used by the rasterizer.
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_ONE: i64 = 1 << SUBPIXEL_BITS;
 
-    // Start x_start and x_end from the top vertex
-    let mut x_start = x0 as f32;
-    let mut x_end = x0 as f32;
+/// Triangles reaching further than this many pixels away from the screen are not rasterized, so
+/// that the fixed-point edge functions can never overflow.
+const GUARD_BAND: f32 = (1 << 21) as f32;
 
-    // loop all the scanlines from top to bottom
-    for y in y0..(ym + 1) {
-        draw_line(frame, x_start as i32, y, x_end as i32, y, rgba);
-        x_start += inverse_slope1;
-        x_end += inverse_slope2;
-    }
+/// Edge function of the edge v0 -> v1 at point p, in fixed point. It is twice the signed area of
+/// the triangle (v0, v1, p): positive on the inner side of the edge, 0 on the edge itself.
+fn edge_function(v0: (i64, i64), v1: (i64, i64), p: (i64, i64)) -> i64 {
+    (v1.0 - v0.0) * (p.1 - v0.1) - (v1.1 - v0.1) * (p.0 - v0.0)
 }
 
-fn fill_flat_top_triangle(
-    frame: &mut [u8],
-    rgba: [u8; 4],
-    x1: i32,
-    y1: i32,
-    xm: i32,
-    ym: i32,
-    x2: i32,
-    y2: i32,
+/// Whether the edge v0 -> v1 of a positively oriented triangle is a top edge (horizontal, with
+/// the triangle below it) or a left edge. Pixel centers that fall exactly on an edge are only
+/// drawn for top and left edges, so an edge shared by two triangles is drawn exactly once.
+fn is_top_left_edge(v0: (i64, i64), v1: (i64, i64)) -> bool {
+    let top = v0.1 == v1.1 && v1.0 > v0.0;
+    let left = v1.1 < v0.1;
+    top || left
+}
+
+/// Rasterize a triangle with edge functions, calling `visit(x, y, weights)` for the center of
+/// every pixel inside a `width` x `height` viewport that the triangle covers. The weights are
+/// the barycentric weights of the pixel center for each point, in the order of `points`.
+///
+/// Points are snapped to a 1/256th of a pixel and edges follow the top-left fill convention, so
+/// triangles sharing an edge never leave cracks nor draw the same pixel twice. Degenerate
+/// triangles draw nothing.
+pub fn rasterize_triangle(
+    width: u32,
+    height: u32,
+    points: &[Vec4; 3],
+    mut visit: impl FnMut(i32, i32, Vec3),
 ) {
-    // Find the 2 slopes (two triangle legs)
-    //  slope1 = c2 -> c1
-    //  slope2 = c2 -> cm
-    let inverse_slope1: f32 = (x2 - x1) as f32 / (y2 - y1) as f32;
-    let inverse_slope2: f32 = (x2 - xm) as f32 / (y2 - ym) as f32;
+    if points
+        .iter()
+        .any(|p| !(p.x.abs() < GUARD_BAND && p.y.abs() < GUARD_BAND))
+    {
+        return;
+    }
 
-    // Start x_start and x_end from the bottom vertex
-    let mut x_start = x2 as f32;
-    let mut x_end = x2 as f32;
+    // Snap the points to the sub-pixel grid
+    let to_fixed = |p: &Vec4| {
+        (
+            (p.x * SUBPIXEL_ONE as f32).round() as i64,
+            (p.y * SUBPIXEL_ONE as f32).round() as i64,
+        )
+    };
+    let mut v = [
+        to_fixed(&points[0]),
+        to_fixed(&points[1]),
+        to_fixed(&points[2]),
+    ];
 
-    // loop all the scanlines from bottom to top
-    for y in (y1..(y2 + 1)).rev() {
-        draw_line(frame, x_start as i32, y, x_end as i32, y, rgba);
-        x_start -= inverse_slope1;
-        x_end -= inverse_slope2;
+    // Twice the signed area; make the triangle positively oriented so the inside of every edge
+    // is positive, remembering to hand the weights back in the original order
+    let mut area = edge_function(v[0], v[1], v[2]);
+    let flipped = area < 0;
+    if flipped {
+        v.swap(1, 2);
+        area = -area;
+    }
+    if area == 0 {
+        return;
+    }
+
+    // Bounding box of the triangle, clamped to the viewport
+    let min_x = (v.iter().map(|p| p.0).min().unwrap_or(0) >> SUBPIXEL_BITS).max(0);
+    let min_y = (v.iter().map(|p| p.1).min().unwrap_or(0) >> SUBPIXEL_BITS).max(0);
+    let max_x = (v.iter().map(|p| p.0).max().unwrap_or(0) >> SUBPIXEL_BITS).min(width as i64 - 1);
+    let max_y = (v.iter().map(|p| p.1).max().unwrap_or(0) >> SUBPIXEL_BITS).min(height as i64 - 1);
+    if min_x > max_x || min_y > max_y {
+        return;
+    }
+
+    // Edges opposite to each point, and the bias that leaves out pixels lying exactly on
+    // edges that are neither top nor left
+    let edges = [(v[1], v[2]), (v[2], v[0]), (v[0], v[1])];
+    let biases = edges.map(|(v0, v1)| if is_top_left_edge(v0, v1) { 0 } else { -1 });
+
+    // Edge functions at the center of the first pixel, and how they change per pixel step
+    let first_center = (
+        (min_x << SUBPIXEL_BITS) + SUBPIXEL_ONE / 2,
+        (min_y << SUBPIXEL_BITS) + SUBPIXEL_ONE / 2,
+    );
+    let mut row = edges.map(|(v0, v1)| edge_function(v0, v1, first_center));
+    let step_x = edges.map(|(v0, v1)| -(v1.1 - v0.1) * SUBPIXEL_ONE);
+    let step_y = edges.map(|(v0, v1)| (v1.0 - v0.0) * SUBPIXEL_ONE);
+
+    for y in min_y..=max_y {
+        let mut e = row;
+        for x in min_x..=max_x {
+            if e[0] + biases[0] >= 0 && e[1] + biases[1] >= 0 && e[2] + biases[2] >= 0 {
+                let mut weights = Vec3 {
+                    x: e[0] as f32 / area as f32,
+                    y: e[1] as f32 / area as f32,
+                    z: e[2] as f32 / area as f32,
+                };
+                if flipped {
+                    std::mem::swap(&mut weights.y, &mut weights.z);
+                }
+                visit(x as i32, y as i32, weights);
+            }
+            for i in 0..3 {
+                e[i] += step_x[i];
+            }
+        }
+        for i in 0..3 {
+            row[i] += step_y[i];
+        }
     }
 }
 
-/// Draw a filled triangle with the flat-top/flat-bottom method.
+/// Depth test the pixel at (x, y) with the given barycentric weights against the depth buffer,
+/// which holds the depth of the closest pixel drawn so far. When the pixel is closer, its depth
+/// is stored and true is returned.
+fn depth_test_pixel(
+    framebuffer: &mut Framebuffer,
+    x: i32,
+    y: i32,
+    points: &[Vec4; 3],
+    weights: &Vec3,
+) -> bool {
+    // The projected z/w is linear in screen space for both perspective and orthographic
+    // projections, so plain barycentric interpolation gives the exact depth
+    let depth = weights.x * points[0].z + weights.y * points[1].z + weights.z * points[2].z;
+
+    // Smaller depth means closer to the camera
+    let index = (framebuffer.width as usize * y as usize) + x as usize;
+    if depth < framebuffer.depth_buffer[index] {
+        framebuffer.depth_buffer[index] = depth;
+        return true;
+    }
+
+    false
+}
+
+/// Draw a triangle whose pixels get the color returned by `shade`, which is given the barycentric
+/// weights of each pixel. Points are in screen space, with their projected depth in `z` and their
+/// clip-space `w` kept in `w`. With `depth_test` only the pixels closer than what was drawn before
+/// are shaded; without it the pixels are drawn in whatever order the triangles come in.
+pub fn draw_shaded_triangle(
+    framebuffer: &mut Framebuffer,
+    points: &[Vec4; 3],
+    depth_test: bool,
+    mut shade: impl FnMut(&Vec3) -> [u8; 4],
+) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    rasterize_triangle(width, height, points, |x, y, weights| {
+        if depth_test && !depth_test_pixel(framebuffer, x, y, points, &weights) {
+            return;
+        }
+
+        let rgba = shade(&weights);
+        draw_pixel(framebuffer, x as usize, y as usize, rgba);
+    });
+}
+
+/// Draw a filled triangle with a single color, as [`draw_shaded_triangle`] does.
 pub fn draw_filled_triangle(
-    frame: &mut [u8],
-    mut x0: i32,
-    mut y0: i32,
-    mut x1: i32,
-    mut y1: i32,
-    mut x2: i32,
-    mut y2: i32,
+    framebuffer: &mut Framebuffer,
+    points: &[Vec4; 3],
     rgba: [u8; 4],
+    depth_test: bool,
 ) {
-    sort_vertices(&mut x0, &mut y0, &mut x1, &mut y1, &mut x2, &mut y2);
-
-    if y1 == y2 {
-        // Just draw upper half to avoid division by 0.
-        fill_flat_bottom_triangle(frame, rgba, x0, y0, x1, y1, x2, y2);
-    } else if y0 == y1 {
-        // Just draw the bottom half to avoid division by 0.
-        fill_flat_top_triangle(frame, rgba, x0, y0, x1, y1, x2, y2);
-    } else {
-        // Calculate the new vertex (middle point) with triangle similarity
-        let my = y1;
-        let mx = ((x2 - x0) * (y1 - y0)) / (y2 - y0) + x0;
+    draw_shaded_triangle(framebuffer, points, depth_test, |_| rgba);
+}
 
-        // Draw flat-bottom triangle
-        fill_flat_bottom_triangle(frame, rgba, x0, y0, x1, y1, mx, my);
+/// Draw a triangle blending the colors of its points across its pixels, as in Gouraud shading.
+pub fn draw_gouraud_triangle(
+    framebuffer: &mut Framebuffer,
+    points: &[Vec4; 3],
+    colors: &[[u8; 4]; 3],
+    depth_test: bool,
+    interpolation: Interpolation,
+) {
+    draw_shaded_triangle(framebuffer, points, depth_test, |weights| {
+        interpolate_color(colors, weights, points, interpolation)
+    });
+}
 
-        // Draw flat-top triangle
-        fill_flat_top_triangle(frame, rgba, x1, y1, mx, my, x2, y2);
-    }
+/// Draw a triangle mapped with a texture, interpolating the texture coordinates of its points.
+/// Every texel is multiplied by the tint of its pixel, blended from the `tints` of the points,
+/// which carry the lighting.
+pub fn draw_textured_triangle(
+    framebuffer: &mut Framebuffer,
+    points: &[Vec4; 3],
+    texcoords: &[Tex2; 3],
+    texture: &Texture,
+    tints: &[[u8; 4]; 3],
+    depth_test: bool,
+    interpolation: Interpolation,
+) {
+    draw_shaded_triangle(framebuffer, points, depth_test, |weights| {
+        // Interpolate the texture coordinates across the triangle
+        let u = interpolate_attribute(
+            texcoords.map(|texcoord| texcoord.u),
+            weights,
+            points,
+            interpolation,
+        );
+        let v = interpolate_attribute(
+            texcoords.map(|texcoord| texcoord.v),
+            weights,
+            points,
+            interpolation,
+        );
+
+        let texel = texture_sample(texture, u, v);
+        let tint = interpolate_color(tints, weights, points, interpolation);
+        color_multiply(&texel, &tint)
+    });
 }
 
+/// Draw the outline of a triangle.
+#[allow(clippy::too_many_arguments)]
 pub fn draw_triangle(
-    frame: &mut [u8],
+    framebuffer: &mut Framebuffer,
     rgba: [u8; 4],
-    fill: bool,
     x0: i32,
     y0: i32,
     x1: i32,
@@ -149,17 +285,14 @@ pub fn draw_triangle(
     x2: i32,
     y2: i32,
 ) {
-    if fill {
-        draw_filled_triangle(frame, x0, y0, x1, y1, x2, y2, rgba);
-    } else {
-        draw_line(frame, x0, y0, x1, y1, rgba);
-        draw_line(frame, x1, y1, x2, y2, rgba);
-        draw_line(frame, x2, y2, x0, y0, rgba);
-    }
+    draw_line(framebuffer, x0, y0, x1, y1, rgba);
+    draw_line(framebuffer, x1, y1, x2, y2, rgba);
+    draw_line(framebuffer, x2, y2, x0, y0, rgba);
 }
 
+/// Fill the rectangle whose top-left pixel is at (x_pos, y_pos), cut to the framebuffer.
 pub fn draw_rect(
-    frame: &mut [u8],
+    framebuffer: &mut Framebuffer,
     x_pos: usize,
     y_pos: usize,
     width: usize,
@@ -167,24 +300,105 @@ pub fn draw_rect(
     rgba: [u8; 4],
 ) {
     let mut x = x_pos;
-    while x < x_pos + width && x < WIDTH as usize {
+    while x < x_pos + width && x < framebuffer.width as usize {
         let mut y = y_pos;
-        while y < y_pos + height && y < HEIGHT as usize {
-            draw_pixel(frame, x, y, rgba);
+        while y < y_pos + height && y < framebuffer.height as usize {
+            draw_pixel(framebuffer, x, y, rgba);
             y += 1;
         }
         x += 1;
     }
 }
 
-pub fn draw_grid(frame: &mut [u8], rgba: [u8; 4], gap: usize) {
+/// Draw a dot every `gap` pixels across the whole framebuffer.
+pub fn draw_grid(framebuffer: &mut Framebuffer, rgba: [u8; 4], gap: usize) {
     let mut x = gap / 2;
-    while x < WIDTH as usize {
+    while x < framebuffer.width as usize {
         let mut y = gap / 2;
-        while y < HEIGHT as usize {
-            draw_pixel(frame, x, y, rgba);
+        while y < framebuffer.height as usize {
+            draw_pixel(framebuffer, x, y, rgba);
             y += gap;
         }
         x += gap;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32) -> Vec4 {
+        Vec4 {
+            x,
+            y,
+            z: 0.5,
+            w: 1.0,
+        }
+    }
+
+    /// How many times each pixel of a `size` x `size` viewport is visited by the triangles.
+    fn coverage(size: u32, triangles: &[[Vec4; 3]]) -> Vec<u32> {
+        let mut counts = vec![0; (size * size) as usize];
+        for points in triangles.iter() {
+            rasterize_triangle(size, size, points, |x, y, _| {
+                counts[(y as u32 * size + x as u32) as usize] += 1;
+            });
+        }
+        counts
+    }
+
+    #[test]
+    fn shared_edges_are_drawn_once() {
+        // Corners on pixel centers put every pixel along the edges exactly on them
+        let (a, b, c, d) = (
+            point(2.5, 2.5),
+            point(12.5, 2.5),
+            point(12.5, 12.5),
+            point(2.5, 12.5),
+        );
+        let counts = coverage(16, &[[a, b, c], [a, c, d]]);
+
+        // Top and left edges are in, bottom and right edges are out: 10x10 pixels, none twice
+        assert!(counts.iter().all(|&count| count <= 1));
+        assert_eq!(counts.iter().sum::<u32>(), 100);
+    }
+
+    #[test]
+    fn a_fan_around_a_point_leaves_no_gaps_nor_overlaps() {
+        let center = point(8.3, 7.9);
+        let rim = [
+            point(1.2, 1.7),
+            point(8.0, 0.6),
+            point(14.9, 1.1),
+            point(15.2, 8.4),
+            point(14.6, 15.3),
+            point(7.7, 14.8),
+            point(0.9, 15.1),
+            point(1.4, 8.2),
+        ];
+        let fan: Vec<_> = (0..rim.len())
+            .map(|i| [center, rim[i], rim[(i + 1) % rim.len()]])
+            .collect();
+        let whole: Vec<_> = (1..rim.len() - 1)
+            .map(|i| [rim[0], rim[i], rim[i + 1]])
+            .collect();
+
+        let fan_counts = coverage(16, &fan);
+        assert!(fan_counts.iter().all(|&count| count <= 1));
+        assert_eq!(fan_counts, coverage(16, &whole));
+    }
+
+    #[test]
+    fn winding_does_not_change_coverage() {
+        let (a, b, c) = (point(1.3, 2.2), point(13.7, 4.1), point(5.5, 14.6));
+
+        assert_eq!(coverage(16, &[[a, b, c]]), coverage(16, &[[a, c, b]]));
+    }
+
+    #[test]
+    fn degenerate_triangles_draw_nothing() {
+        let (a, b, c) = (point(1.5, 1.5), point(7.5, 7.5), point(13.5, 13.5));
+
+        assert!(coverage(16, &[[a, b, c]]).iter().all(|&count| count == 0));
+    }
+}
@@ -0,0 +1,150 @@
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::framebuffer::{buffer_lengths, Framebuffer};
+
+/// Check that `rgba` holds exactly the pixels of a `width` x `height` image.
+fn check_image_size(width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+    match buffer_lengths(width, height) {
+        Ok((_, bytes)) if bytes == rgba.len() => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} bytes of RGBA pixels do not make a {width}x{height} image",
+                rgba.len()
+            ),
+        )),
+    }
+}
+
+/// Write RGBA pixels, stored row by row from the top-left, as a binary .ppm (P6) file. PPM has
+/// no alpha channel, so alpha is dropped.
+pub fn save_ppm(filename: String, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+    check_image_size(width, height, rgba)?;
+
+    let mut writer = BufWriter::new(fs::File::create(filename)?);
+    write!(writer, "P6\n{width} {height}\n255\n")?;
+    for pixel in rgba.chunks_exact(4) {
+        writer.write_all(&pixel[..3])?;
+    }
+
+    writer.flush()
+}
+
+/// Write RGBA pixels, stored row by row from the top-left, as a .png file.
+pub fn save_png(
+    filename: String,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> Result<(), png::EncodingError> {
+    check_image_size(width, height, rgba)?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(fs::File::create(filename)?), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(rgba)?;
+    writer.finish()
+}
+
+/// Name made of `prefix`, the current UTC date and time and `extension`, such as
+/// `screenshot-20240131-235959-042.png`.
+pub fn timestamped_filename(prefix: &str, extension: &str) -> String {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    filename_at(prefix, extension, since_epoch)
+}
+
+/// Name made of `prefix`, the UTC date and time `since_epoch` after 1970-01-01 and `extension`.
+fn filename_at(prefix: &str, extension: &str, since_epoch: Duration) -> String {
+    let seconds = since_epoch.as_secs() as i64;
+    let millis = since_epoch.subsec_millis();
+
+    // Convert the days since 1970-01-01 into a civil date (proleptic Gregorian calendar)
+    let days = seconds.div_euclid(86400);
+    let time_of_day = seconds.rem_euclid(86400);
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{prefix}-{year:04}{month:02}{day:02}-{:02}{:02}{:02}-{millis:03}.{extension}",
+        time_of_day / 3600,
+        time_of_day / 60 % 60,
+        time_of_day % 60,
+    )
+}
+
+/// Save the color buffer of the framebuffer as a .png file with a timestamped name in the
+/// current directory and, with `include_depth`, the depth buffer as grayscale next to it.
+/// Returns the names of the files written.
+pub fn save_screenshot(
+    framebuffer: &Framebuffer,
+    include_depth: bool,
+) -> Result<Vec<String>, png::EncodingError> {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let color_file = timestamped_filename("screenshot", "png");
+    save_png(color_file.clone(), width, height, &framebuffer.color_buffer)?;
+
+    let mut files = vec![color_file];
+    if include_depth {
+        let depth_file = format!("{}-depth.png", files[0].trim_end_matches(".png"));
+        save_png(
+            depth_file.clone(),
+            width,
+            height,
+            &framebuffer.depth_to_grayscale(),
+        )?;
+        files.push(depth_file);
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filenames_carry_the_utc_date_and_time() {
+        let at = |seconds: u64, millis: u64| {
+            let since_epoch = Duration::from_secs(seconds) + Duration::from_millis(millis);
+            filename_at("shot", "png", since_epoch)
+        };
+
+        assert_eq!(at(0, 0), "shot-19700101-000000-000.png");
+        // Leap days, including the one of a year divisible by 400
+        assert_eq!(at(951_782_400, 0), "shot-20000229-000000-000.png");
+        assert_eq!(at(1_709_251_199, 999), "shot-20240229-235959-999.png");
+        // The turn of a year
+        assert_eq!(at(1_704_067_199, 500), "shot-20231231-235959-500.png");
+        assert_eq!(at(1_704_067_200, 0), "shot-20240101-000000-000.png");
+        // 2100 is not a leap year, so February ends on the 28th
+        assert_eq!(at(4_107_542_400, 42), "shot-21000301-000000-042.png");
+    }
+
+    #[test]
+    fn pixels_must_match_the_image_size() {
+        assert!(check_image_size(2, 3, &[0; 24]).is_ok());
+        assert!(check_image_size(2, 3, &[0; 20]).is_err());
+
+        // Sizes whose byte count overflows are refused, not wrapped around
+        assert!(check_image_size(u32::MAX, u32::MAX, &[]).is_err());
+        assert!(check_image_size(40000, 30000, &[0; 16]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use crate::consts::{HEIGHT, WIDTH};
+use crate::display::{clear_color_buffer, clear_depth_buffer};
+
+/// An owned RGBA color buffer that frames are rendered into, independent of any window surface,
+/// together with a depth buffer holding the depth of the closest pixel drawn so far, from 0.0 at
+/// the near plane to 1.0 at the far plane.
+pub struct Framebuffer {
+    pub width: u32,
+    pub height: u32,
+    pub color_buffer: Vec<u8>,
+    pub depth_buffer: Vec<f32>,
+}
+
+/// The resolution asked of a framebuffer is too large for its buffers to be addressed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FramebufferSizeError {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl fmt::Display for FramebufferSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} framebuffer is too large",
+            self.width, self.height
+        )
+    }
+}
+
+impl std::error::Error for FramebufferSizeError {}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Framebuffer::new(WIDTH, HEIGHT).expect("the default resolution fits in memory")
+    }
+}
+
+/// Number of pixels of a `width` x `height` framebuffer, and the length of its RGBA color buffer.
+pub(crate) fn buffer_lengths(
+    width: u32,
+    height: u32,
+) -> Result<(usize, usize), FramebufferSizeError> {
+    let pixels = (width as usize).checked_mul(height as usize);
+    match pixels.and_then(|pixels| Some((pixels, pixels.checked_mul(4)?))) {
+        Some(lengths) => Ok(lengths),
+        None => Err(FramebufferSizeError { width, height }),
+    }
+}
+
+impl Framebuffer {
+    /// Create a framebuffer of `width` x `height` pixels.
+    pub fn new(width: u32, height: u32) -> Result<Self, FramebufferSizeError> {
+        let (pixels, bytes) = buffer_lengths(width, height)?;
+
+        Ok(Framebuffer {
+            width,
+            height,
+            color_buffer: vec![0; bytes],
+            depth_buffer: vec![1.0; pixels],
+        })
+    }
+
+    /// Change the resolution of the framebuffer. The contents are left undefined until the next
+    /// clear. On error the framebuffer keeps its previous resolution.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), FramebufferSizeError> {
+        let (pixels, bytes) = buffer_lengths(width, height)?;
+
+        self.width = width;
+        self.height = height;
+        self.color_buffer.resize(bytes, 0);
+        self.depth_buffer.resize(pixels, 1.0);
+        Ok(())
+    }
+
+    /// Width over height of the framebuffer.
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+
+    /// Fill the whole color buffer with a single color and reset the depth buffer.
+    pub fn clear(&mut self, rgba: [u8; 4]) {
+        clear_color_buffer(&mut self.color_buffer, rgba);
+        clear_depth_buffer(&mut self.depth_buffer);
+    }
+
+    /// The depth buffer as RGBA grayscale pixels: the closest pixel drawn is white, the farthest
+    /// one is dark gray and pixels nothing was drawn on are black. Only frames drawn with the
+    /// depth buffer enabled have depth to show.
+    pub fn depth_to_grayscale(&self) -> Vec<u8> {
+        // Stretch the range of depths actually drawn, which is usually a thin slice of 0.0 - 1.0
+        let (near, far) = self
+            .depth_buffer
+            .iter()
+            .filter(|&&depth| depth < 1.0)
+            .fold((f32::MAX, f32::MIN), |(near, far), &depth| {
+                (near.min(depth), far.max(depth))
+            });
+        let range = (far - near).max(f32::EPSILON);
+
+        self.depth_buffer
+            .iter()
+            .flat_map(|&depth| {
+                let gray = if depth < 1.0 {
+                    (255.0 - 223.0 * (depth - near) / range) as u8
+                } else {
+                    0
+                };
+                [gray, gray, gray, 0xff]
+            })
+            .collect()
+    }
+}
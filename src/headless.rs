@@ -0,0 +1,154 @@
+use crate::camera::Camera;
+use crate::consts::{BACKGROUND_COLOR, HEIGHT, WIDTH};
+use crate::framebuffer::{Framebuffer, FramebufferSizeError};
+use crate::light::Light;
+use crate::mesh::{mesh_world_matrix, Mesh};
+use crate::pipeline::{
+    draw_triangles, project_mesh, sort_triangles_by_depth, DepthMode, RenderSettings,
+};
+use crate::scene::Scene;
+use crate::triangle::Triangle;
+
+/// Renders frames into an owned `Framebuffer` without opening a window or a GPU surface.
+pub struct HeadlessRenderer {
+    pub settings: RenderSettings,
+    framebuffer: Framebuffer,
+    triangles_to_render: Vec<Triangle>,
+}
+
+impl Default for HeadlessRenderer {
+    fn default() -> Self {
+        Self::new(WIDTH, HEIGHT).expect("the default resolution fits in memory")
+    }
+}
+
+impl HeadlessRenderer {
+    /// Create a new `HeadlessRenderer` with a framebuffer of `width` x `height` pixels.
+    pub fn new(width: u32, height: u32) -> Result<Self, FramebufferSizeError> {
+        Ok(Self {
+            settings: RenderSettings {
+                ..Default::default()
+            },
+            framebuffer: Framebuffer::new(width, height)?,
+            triangles_to_render: Vec::new(),
+        })
+    }
+
+    /// Change the output resolution. The projection follows the new aspect ratio.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), FramebufferSizeError> {
+        self.framebuffer.resize(width, height)
+    }
+
+    /// Render one frame of the given meshes, seen from `camera` and lit by `lights`, and return
+    /// a copy of the resulting RGBA pixels.
+    pub fn render(&mut self, meshes: &[Mesh], camera: &Camera, lights: &[Light]) -> Vec<u8> {
+        self.triangles_to_render.clear();
+        for mesh in meshes.iter() {
+            project_mesh(
+                mesh,
+                &mesh_world_matrix(mesh),
+                camera,
+                lights,
+                self.framebuffer.width,
+                self.framebuffer.height,
+                &mut self.triangles_to_render,
+            );
+        }
+
+        self.draw_frame(camera, lights)
+    }
+
+    /// Render one frame of every instance of the scene, seen from `camera` and lit by the lights
+    /// of the scene, and return a copy of the resulting RGBA pixels.
+    pub fn render_scene(&mut self, scene: &Scene, camera: &Camera) -> Vec<u8> {
+        self.triangles_to_render.clear();
+        scene.project(
+            camera,
+            self.framebuffer.width,
+            self.framebuffer.height,
+            &mut self.triangles_to_render,
+        );
+
+        self.draw_frame(camera, &scene.lights)
+    }
+
+    /// Rasterize the projected triangles into a cleared framebuffer and return a copy of it.
+    fn draw_frame(&mut self, camera: &Camera, lights: &[Light]) -> Vec<u8> {
+        if self.settings.depth_mode == DepthMode::Painter {
+            sort_triangles_by_depth(&mut self.triangles_to_render);
+        }
+
+        self.framebuffer.clear(BACKGROUND_COLOR);
+        draw_triangles(
+            &mut self.framebuffer,
+            &self.triangles_to_render,
+            camera,
+            lights,
+            &self.settings,
+        );
+
+        self.framebuffer.color_buffer.clone()
+    }
+
+    /// The framebuffer holding the last rendered frame.
+    pub fn framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::light::Light;
+    use crate::mesh::load_obj_file_data;
+    use std::sync::Arc;
+
+    /// Count the pixels of an RGBA frame that are not the background color.
+    fn covered_pixels(pixels: &[u8]) -> usize {
+        pixels
+            .chunks_exact(4)
+            .filter(|pixel| *pixel != BACKGROUND_COLOR)
+            .count()
+    }
+
+    #[test]
+    fn cube_renders_the_same_in_both_depth_modes() {
+        let mut scene = Scene::default();
+        scene.add(Arc::new(
+            load_obj_file_data("assets/cube.obj".to_string()).unwrap(),
+        ));
+        scene.lights.push(Light::ambient(1.0));
+
+        // Looking down at a corner, so three faces are visible
+        let mut camera = Camera {
+            yaw: 0.6,
+            pitch: -0.4,
+            ..Default::default()
+        };
+        camera.frame(&scene.bounding_box().unwrap(), 1.0);
+
+        let mut renderer = HeadlessRenderer::new(32, 32).unwrap();
+        let mut frames = Vec::new();
+        for depth_mode in [DepthMode::ZBuffer, DepthMode::Painter] {
+            renderer.settings.depth_mode = depth_mode;
+            let pixels = renderer.render_scene(&scene, &camera);
+            assert_eq!(pixels.len(), 32 * 32 * 4);
+
+            // The cube fills the middle of the frame and leaves the corners empty
+            let covered = covered_pixels(&pixels);
+            assert!(
+                covered > 32 * 32 / 4,
+                "{depth_mode:?}: {covered} pixels covered"
+            );
+            assert!(covered < 32 * 32, "{depth_mode:?}: no background left");
+            assert_eq!(pixels[..4], BACKGROUND_COLOR);
+            let center = (16 * 32 + 16) * 4;
+            assert_ne!(pixels[center..center + 4], BACKGROUND_COLOR);
+
+            frames.push(pixels);
+        }
+
+        // A convex mesh has no overlapping front faces, so the sorting must not matter
+        assert_eq!(frames[0], frames[1]);
+    }
+}
@@ -1,344 +1,353 @@
+//! A basic pixel-by-pixel 3d renderer.
+//!
+//! The crate can be used in two ways:
+//! - [`run`] opens a window and renders a small scene in real time.
+//! - The modules below form a software rendering library: load meshes with
+//!   [`mesh::load_obj_file_data`], transform them with the [`matrix`] and [`vector`] functions,
+//!   draw into RGBA buffers with [`display`], or render whole frames of a [`Scene`] with
+//!   [`HeadlessRenderer`].
+//!
+//! Most programs only need `use frug3d::prelude::*;`.
 #![deny(clippy::all)]
 #![forbid(unsafe_code)]
 
-mod consts;
-mod display;
-mod helpers;
-mod light;
-mod matrix;
-mod mesh;
-mod triangle;
-mod vector;
-
-use std::f32::consts::PI;
-use std::mem::swap;
+pub mod camera;
+pub(crate) mod clipping;
+pub mod consts;
+pub(crate) mod controls;
+pub mod display;
+pub mod export;
+pub mod framebuffer;
+pub mod headless;
+pub mod light;
+pub mod material;
+pub mod matrix;
+pub mod mesh;
+pub(crate) mod pipeline;
+pub mod prelude;
+pub mod recording;
+pub mod scene;
+pub mod texture;
+pub(crate) mod timing;
+pub(crate) mod triangle;
+pub mod vector;
+
+pub use camera::{AxisView, Camera, Projection};
+pub use framebuffer::{Framebuffer, FramebufferSizeError};
+pub use headless::HeadlessRenderer;
+pub use light::{Light, LightKind};
+pub use material::Material;
+pub use matrix::Mat4;
+pub use mesh::{load_obj_file_data, Mesh, MeshLoadError};
+pub use pipeline::{DepthMode, RenderSettings, Shading};
+pub use scene::{MeshInstance, NodeId, Scene, SceneNode};
+pub use texture::{load_png_texture, Tex2, Texture};
+pub use timing::FramePacing;
+pub use triangle::{Face, Interpolation};
+pub use vector::{Vec2, Vec3, Vec4};
+
+use std::sync::Arc;
 use std::time::Instant;
 
 use consts::*;
-use display::{
-    clear_color_buffer, draw_filled_triangle, draw_grid, draw_line, draw_pixel, draw_rect,
-    draw_triangle,
-};
+use controls::{CameraMode, FlyController, OrbitController};
 use error_iter::ErrorIter as _;
-use light::{light_apply_intensity, Light};
-use log::error;
-use matrix::{
-    mat4_identity, mat4_make_perspective, mat4_make_rotation_x, mat4_make_rotation_y,
-    mat4_make_rotation_z, mat4_make_scale, mat4_make_translation, mat4_mul_mat4, mat4_mul_vec4,
-    mat4_mul_vec4_project, Mat4,
-};
-use mesh::{load_obj_file_data, Mesh};
-use pixels::{Error, Pixels, SurfaceTexture};
+use export::save_screenshot;
+use log::{error, info};
+use pipeline::{draw_triangles, sort_triangles_by_depth};
+use pixels::{PixelsBuilder, SurfaceTexture};
+use timing::{FixedTimestep, FrameLimiter};
 use triangle::Triangle;
-use vector::{vec3_from_vec4, vec4_from_vec3, Vec2, Vec3, Vec4};
+use vector::{vec3_add, vec3_mul, vec3_sub};
 use winit::dpi::LogicalSize;
 use winit::event::{Event, VirtualKeyCode};
 use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::WindowBuilder;
 use winit_input_helper::WinitInputHelper;
 
-use crate::vector::{
-    vec3_cross, vec3_dot, vec3_normalize, vec3_rotate_x, vec3_rotate_y, vec3_rotate_z, vec3_sub,
-};
-
-/// Representation of the application state. In this example, a box will bounce around the screen.
-struct Renderer {
-    is_running: bool,
-    camera_pos: Vec3,
-    projection_matrix: Mat4,
-    mesh: Mesh,
-    triangles_to_render: Vec<Triangle>,
+/// Options of the window opened by [`run_viewer`].
+pub struct ViewerOptions {
+    pub title: String,
+    /// Initial size of the window, in logical pixels.
+    pub width: u32,
+    pub height: u32,
+    pub settings: RenderSettings,
+    /// Nodes turned at the given speed, in radians per second, to animate the scene.
+    pub spinning_nodes: Vec<(NodeId, Vec3)>,
+    pub pacing: FramePacing,
+    /// Length of a simulation step, in seconds. Animations advance by whole steps, whatever the
+    /// frame rate. `run_viewer` returns an error unless it is positive and finite.
+    pub update_step: f32,
 }
 
-impl Renderer {
-    /// Create a new `Renderer` instance that can draw a moving box.
-    fn new() -> Self {
-        let mesh =
-            load_obj_file_data("assets/cube.obj".to_string()).expect("Error reading object data");
-
-        let fov = PI / 3.0; // 180 / 3 = 60 degrees.
-        let aspect = HEIGHT as f32 / WIDTH as f32;
-        let znear = 0.1;
-        let zfar = 100.0;
-        let projection_matrix = mat4_make_perspective(fov, aspect, znear, zfar);
-
-        Self {
-            is_running: true,
-            camera_pos: Vec3 {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
+impl Default for ViewerOptions {
+    fn default() -> Self {
+        ViewerOptions {
+            title: "frug3d".to_string(),
+            width: WIDTH,
+            height: HEIGHT,
+            settings: RenderSettings {
+                ..Default::default()
             },
-            projection_matrix,
-            mesh,
-            triangles_to_render: Vec::new(),
+            spinning_nodes: Vec::new(),
+            pacing: FramePacing::TargetFps(FPS),
+            update_step: FRAME_TARGET_TIME / 1000.0,
         }
     }
+}
 
-    /// Update the `Renderer` internal state; bounce the box around the screen.
-    fn update(&mut self) {
-        // control FPS by waiting the frame target time
-        //todo!();
-
-        // Clear array of triangles
-        self.triangles_to_render.clear();
-
-        // add rotation (temporal)
-        self.mesh.rotation.x += 0.01;
-        self.mesh.rotation.y += 0.01;
-        self.mesh.rotation.z += 0.01;
-
-        // change scale (temporal)
-        //self.mesh.scale.x += 0.002;
-        //self.mesh.scale.y += 0.001;
+/// Representation of the application state: the scene, the camera looking at it and the
+/// controllers that move the camera around.
+struct Renderer {
+    settings: RenderSettings,
+    framebuffer: Framebuffer,
+    camera: Camera,
+    camera_mode: CameraMode,
+    fly_controller: FlyController,
+    orbit_controller: OrbitController,
+    scene: Scene,
+    spinning_nodes: Vec<(NodeId, Vec3)>,
+    /// Rotation of each spinning node at the previous simulation step, to interpolate from.
+    previous_rotations: Vec<Vec3>,
+    triangles_to_render: Vec<Triangle>,
+}
 
-        // light (temporal)
-        let light_dir = Light {
-            direction: Vec3 {
-                x: 0.0,
-                y: 0.0,
-                z: 1.0,
-            },
+impl Renderer {
+    /// Create a new `Renderer` instance that draws the scene, seen from `camera`, into a
+    /// `width` x `height` frame.
+    fn new(
+        scene: Scene,
+        camera: Camera,
+        options: &ViewerOptions,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, FramebufferSizeError> {
+        // Size the controllers after the scene, so any model is easy to move around
+        let mut fly_controller = FlyController {
+            ..Default::default()
         };
-
-        // translate the vertex away from the camera
-        self.mesh.translation.z = 5.0;
-
-        // Change translation
-        //self.mesh.translation.x += 0.01;
-
-        // Create transformation matrix
-        let mut transformation_matrix = mat4_identity();
-        {
-            let scale_matrix =
-                mat4_make_scale(self.mesh.scale.x, self.mesh.scale.y, self.mesh.scale.z);
-            let translation_matrix = mat4_make_translation(
-                self.mesh.translation.x,
-                self.mesh.translation.y,
-                self.mesh.translation.z,
-            );
-            let rotation_x_matrix = mat4_make_rotation_x(self.mesh.rotation.x);
-            let rotation_y_matrix = mat4_make_rotation_y(self.mesh.rotation.y);
-            let rotation_z_matrix = mat4_make_rotation_z(self.mesh.rotation.z);
-
-            // Note: the order of these multiplications matters!! scale -> rotate -> translate
-            transformation_matrix = mat4_mul_mat4(&scale_matrix, &transformation_matrix);
-            transformation_matrix = mat4_mul_mat4(&rotation_x_matrix, &transformation_matrix);
-            transformation_matrix = mat4_mul_mat4(&rotation_y_matrix, &transformation_matrix);
-            transformation_matrix = mat4_mul_mat4(&rotation_z_matrix, &transformation_matrix);
-            transformation_matrix = mat4_mul_mat4(&translation_matrix, &transformation_matrix);
+        let mut orbit_controller = OrbitController {
+            ..Default::default()
+        };
+        if let Some(bounds) = scene.bounding_box() {
+            fly_controller.move_speed = bounds.radius();
+            orbit_controller = OrbitController::framing(&bounds, &camera);
         }
 
-        // loop all triangle faces
-        for mesh_face in self.mesh.faces.iter() {
-            let mut face_vertices: [Vec3; 3] = [
-                {
-                    Vec3 {
-                        ..Default::default()
-                    }
-                },
-                {
-                    Vec3 {
-                        ..Default::default()
-                    }
-                },
-                {
-                    Vec3 {
-                        ..Default::default()
-                    }
-                },
-            ];
-
-            face_vertices[0] = self.mesh.vertices[(mesh_face.a - 1) as usize];
-            face_vertices[1] = self.mesh.vertices[(mesh_face.b - 1) as usize];
-            face_vertices[2] = self.mesh.vertices[(mesh_face.c - 1) as usize];
-
-            let mut transformed_vertices: [Vec4; 3] = [
-                {
-                    Vec4 {
-                        ..Default::default()
-                    }
-                },
-                {
-                    Vec4 {
-                        ..Default::default()
-                    }
-                },
-                {
-                    Vec4 {
-                        ..Default::default()
-                    }
-                },
-            ];
-
-            // * loop all 3 vertices of this current face and apply transformations *
-            for j in 0..3 {
-                let mut transformed_vertex = vec4_from_vec3(&face_vertices[j]);
-
-                // Use a matrix to transform our original vertex
-                transformed_vertex = mat4_mul_vec4(&transformation_matrix, &transformed_vertex);
-
-                // save transformed vertex
-                transformed_vertices[j] = transformed_vertex;
-            }
-
-            // * Check backface culling *
-            let vec_a = vec3_from_vec4(&transformed_vertices[0]);
-            let vec_b = vec3_from_vec4(&transformed_vertices[1]);
-            let vec_c = vec3_from_vec4(&transformed_vertices[2]);
-
-            let mut vec_ab = vec3_sub(&vec_b, &vec_a); // B-A
-            let mut vec_ac = vec3_sub(&vec_c, &vec_a); // C-A
-            vec3_normalize(&mut vec_ab);
-            vec3_normalize(&mut vec_ac);
-
-            let mut normal = vec3_cross(&vec_ab, &vec_ac); // Use cross prod to find perpendicular.
-            vec3_normalize(&mut normal); // normalize normal vector
-
-            let cam_ray = vec3_sub(&self.camera_pos, &vec_a);
-
-            // Negative dot product -> not looking towards camera
-            let dot_normal_cam = vec3_dot(&normal, &cam_ray);
-
-            if dot_normal_cam < 0.0 {
-                // Bypass the triangles that are not looking at the camera
-                continue;
-            }
+        let previous_rotations = options
+            .spinning_nodes
+            .iter()
+            .map(|(node, _)| scene.node(*node).rotation)
+            .collect();
+
+        Ok(Self {
+            settings: options.settings,
+            framebuffer: Framebuffer::new(width, height)?,
+            camera,
+            camera_mode: CameraMode::Orbit,
+            fly_controller,
+            orbit_controller,
+            scene,
+            previous_rotations,
+            spinning_nodes: options.spinning_nodes.clone(),
+            triangles_to_render: Vec::new(),
+        })
+    }
 
-            // * Loop all 3 vertices to perform projection
-            let mut projected_triangle: Triangle = Triangle {
-                ..Default::default()
-            };
-            for j in 0..3 {
-                // project the current vertex
-                let mut projected_point =
-                    mat4_mul_vec4_project(&self.projection_matrix, &transformed_vertices[j]);
-
-                // Scale into the view
-                projected_point.x *= WIDTH as f32 / 2.0;
-                projected_point.y *= HEIGHT as f32 / 2.0;
-
-                // translate the projected points to the middle of the screen
-                projected_point.x += (WIDTH / 2) as f32;
-                projected_point.y += (HEIGHT / 2) as f32;
-
-                // save that point
-                projected_triangle.points[j] = Vec2 {
-                    x: projected_point.x,
-                    y: projected_point.y,
-                };
-                projected_triangle.avg_depth += transformed_vertices[j].z;
+    /// Switch between the fly and the orbit camera. The orbit restarts around the scene, from
+    /// wherever the camera was left.
+    fn toggle_camera_mode(&mut self) {
+        self.camera_mode = match self.camera_mode {
+            CameraMode::Fly => {
+                if let Some(bounds) = self.scene.bounding_box() {
+                    self.orbit_controller = OrbitController::framing(&bounds, &self.camera);
+                }
+                CameraMode::Orbit
             }
+            CameraMode::Orbit => CameraMode::Fly,
+        };
+    }
 
-            // Finish calculating the averga depth for each face based on the vertices after transformation.
-            projected_triangle.avg_depth /= 3.0;
+    /// Look at the scene along one of the world axes, keeping the active controller in sync.
+    fn set_axis_view(&mut self, view: AxisView) {
+        if let Some(bounds) = self.scene.bounding_box() {
+            self.camera
+                .frame_axis_view(view, &bounds, self.framebuffer.aspect_ratio());
+            self.orbit_controller = OrbitController::framing(&bounds, &self.camera);
+        }
+    }
 
-            // Calculate color based on light
-            let light_intensity_factor = -vec3_dot(&normal, &light_dir.direction);
-            println!(
-                "color: [{}, {}, {}]",
-                mesh_face.rgba[0], mesh_face.rgba[1], mesh_face.rgba[2]
-            );
+    /// Move the camera with the active controller. `dt` is the real time since the last update,
+    /// in seconds.
+    fn update_camera(&mut self, input: &WinitInputHelper, dt: f32) {
+        match self.camera_mode {
+            CameraMode::Fly => self.fly_controller.update(&mut self.camera, input, dt),
+            CameraMode::Orbit => self.orbit_controller.update(&mut self.camera, input, dt),
+        }
+    }
 
-            projected_triangle.rgba =
-                light_apply_intensity(&mesh_face.rgba, light_intensity_factor);
+    /// Resize the render target. The projection follows the new aspect ratio.
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), FramebufferSizeError> {
+        self.framebuffer.resize(width, height)
+    }
 
-            println!(
-                "light: [{}, {}, {}]",
-                projected_triangle.rgba[0], projected_triangle.rgba[1], projected_triangle.rgba[2]
-            );
+    /// Advance the simulation by one step of `dt` seconds; spin the animated nodes.
+    fn update(&mut self, dt: f32) {
+        for ((node, spin), previous) in self
+            .spinning_nodes
+            .iter()
+            .zip(self.previous_rotations.iter_mut())
+        {
+            let node = self.scene.node_mut(*node);
+            *previous = node.rotation;
+            node.rotation = vec3_add(&node.rotation, &vec3_mul(spin, dt));
+        }
+    }
 
-            println!("factor: {light_intensity_factor}");
+    /// Draw the `Renderer` state to the frame buffer. `alpha` tells how far the frame is between
+    /// the last two simulation steps (0.0 - 1.0), to interpolate the animation smoothly.
+    ///
+    /// Assumes the default texture format: `wgpu::TextureFormat::Rgba8UnormSrgb`
+    fn draw(&mut self, frame: &mut [u8], alpha: f32) {
+        // Clear array of triangles
+        self.triangles_to_render.clear();
 
-            // save the projected triangle in the array of triangles to render
-            // triangles_to_render[i] = projected_triangle;
-            self.triangles_to_render.push(projected_triangle);
+        // Move the animated nodes in between their last two steps, keeping the latest step
+        let latest_rotations: Vec<Vec3> = self
+            .spinning_nodes
+            .iter()
+            .zip(self.previous_rotations.iter())
+            .map(|((node, _), previous)| {
+                let node = self.scene.node_mut(*node);
+                let latest = node.rotation;
+                let delta = vec3_sub(&latest, previous);
+                node.rotation = vec3_add(previous, &vec3_mul(&delta, alpha));
+                latest
+            })
+            .collect();
+
+        self.scene.project(
+            &self.camera,
+            self.framebuffer.width,
+            self.framebuffer.height,
+            &mut self.triangles_to_render,
+        );
+
+        for ((node, _), latest) in self.spinning_nodes.iter().zip(latest_rotations) {
+            self.scene.node_mut(*node).rotation = latest;
         }
 
-        // Sort the triangles to render by their avg_depth
-        //  TODO: THIS IS NOT OPTIMAL AND COULD *EASILY* BE OPTIMIZED WITH A BETTER ALGORITHM. (using bubble sort now)
-        for i in 0..self.triangles_to_render.len() {
-            for j in i..self.triangles_to_render.len() {
-                if self.triangles_to_render[i].avg_depth < self.triangles_to_render[j].avg_depth {
-                    // swap the triangles
-                    let temp = self.triangles_to_render[i].clone();
-                    self.triangles_to_render[i] = self.triangles_to_render[j];
-                    self.triangles_to_render[i] = temp;
-                }
-            }
+        if self.settings.depth_mode == DepthMode::Painter {
+            sort_triangles_by_depth(&mut self.triangles_to_render);
         }
-    }
 
-    /// Draw the `Renderer` state to the frame buffer.
-    ///
-    /// Assumes the default texture format: `wgpu::TextureFormat::Rgba8UnormSrgb`
-    fn draw(&mut self, frame: &mut [u8]) {
         // Clear screen
-        clear_color_buffer(frame, BACKGROUND_COLOR);
+        self.framebuffer.clear(BACKGROUND_COLOR);
 
         // * draw stuff here *
         // loop all projected triangles to render
-        for triangle in self.triangles_to_render.iter() {
-            // draw filled faces
-            draw_triangle(
-                frame,
-                triangle.rgba,
-                true,
-                triangle.points[0].x as i32,
-                triangle.points[0].y as i32,
-                triangle.points[1].x as i32,
-                triangle.points[1].y as i32,
-                triangle.points[2].x as i32,
-                triangle.points[2].y as i32,
-            );
-
-            // draw edges
-            /*draw_triangle(
-                frame,
-                C_GREEN,
-                false,
-                triangle.points[0].x as i32,
-                triangle.points[0].y as i32,
-                triangle.points[1].x as i32,
-                triangle.points[1].y as i32,
-                triangle.points[2].x as i32,
-                triangle.points[2].y as i32,
-            );*/
-        }
-
-        // Clear the array of triangles to render every frame
-        self.triangles_to_render.clear();
+        draw_triangles(
+            &mut self.framebuffer,
+            &self.triangles_to_render,
+            &self.camera,
+            &self.scene.lights,
+            &self.settings,
+        );
+
+        // Present the rendered frame
+        frame.copy_from_slice(&self.framebuffer.color_buffer);
     }
 }
 
-pub fn run() -> Result<(), Error> {
+/// Open a window and render the demo scene in real time until the window is closed. See
+/// [`run_viewer`] for the controls.
+pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
+    let (scene, spinning_nodes) = load_demo_scene()?;
+
+    // Look slightly down at the scene, from its front left
+    let mut camera = Camera {
+        yaw: 0.6,
+        pitch: -0.35,
+        ..Default::default()
+    };
+    if let Some(bounds) = scene.bounding_box() {
+        camera.frame(&bounds, WIDTH as f32 / HEIGHT as f32);
+    }
+
+    run_viewer(
+        scene,
+        camera,
+        ViewerOptions {
+            spinning_nodes,
+            ..Default::default()
+        },
+    )
+}
+
+/// Open a window and render the scene, starting from `camera`, in real time until the window is
+/// closed.
+///
+/// Press C to switch between the orbit camera (drag to orbit, scroll to zoom) and the fly camera
+/// (WASD to move, E/Q to go up and down, drag to look around). P toggles the orthographic
+/// projection and 1, 2 and 3 show the front, side and top views of the scene. F12 saves a
+/// screenshot, and shift+F12 its depth buffer too.
+pub fn run_viewer(
+    scene: Scene,
+    camera: Camera,
+    options: ViewerOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Keep the logger of the program, if it set one up before loading its meshes
+    let _ = env_logger::try_init();
+    if !options.update_step.is_finite() || options.update_step <= 0.0 {
+        return Err(format!(
+            "update step must be a positive number of seconds, got {}",
+            options.update_step
+        )
+        .into());
+    }
+
     let event_loop = EventLoop::new();
     let mut input = WinitInputHelper::new();
 
     // Window setup
     let window = {
-        let size = LogicalSize::new(WIDTH as f64, HEIGHT as f64);
+        let size = LogicalSize::new(options.width as f64, options.height as f64);
         WindowBuilder::new()
-            .with_title("Hello Pixels")
+            .with_title(&options.title)
             .with_inner_size(size)
-            .with_min_inner_size(size)
-            .build(&event_loop)
-            .unwrap()
+            .build(&event_loop)?
     };
 
+    let window_size = window.inner_size();
     let mut pixels = {
-        let window_size = window.inner_size();
         let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(WIDTH, HEIGHT, surface_texture)?
+        PixelsBuilder::new(window_size.width, window_size.height, surface_texture)
+            .enable_vsync(options.pacing == FramePacing::VSync)
+            .build()?
     };
 
-    let mut renderer = Renderer::new();
+    let mut renderer = Renderer::new(
+        scene,
+        camera,
+        &options,
+        window_size.width,
+        window_size.height,
+    )?;
+
+    // Frame pacing and simulation clock
+    let mut frame_limiter = match options.pacing {
+        FramePacing::TargetFps(fps) => Some(FrameLimiter::new(fps)),
+        FramePacing::VSync | FramePacing::Unlimited => None,
+    };
+    let mut timestep = FixedTimestep::new(options.update_step);
+    let mut last_input = Instant::now();
+    let mut last_frame = Instant::now();
 
     event_loop.run(move |event, _, control_flow| {
         // Draw the current frame
         if let Event::RedrawRequested(_) = event {
-            renderer.draw(pixels.frame_mut());
+            renderer.draw(pixels.frame_mut(), timestep.alpha());
             if let Err(err) = pixels.render() {
                 log_error("pixels.render", err);
                 *control_flow = ControlFlow::Exit;
@@ -354,22 +363,218 @@ pub fn run() -> Result<(), Error> {
                 return;
             }
 
-            // Resize the window
-            if let Some(size) = input.window_resized() {
+            // Toggle between the depth buffer and the painter's algorithm
+            if input.key_pressed(VirtualKeyCode::Z) {
+                renderer.settings.depth_mode = match renderer.settings.depth_mode {
+                    DepthMode::ZBuffer => DepthMode::Painter,
+                    DepthMode::Painter => DepthMode::ZBuffer,
+                };
+            }
+
+            // Toggle between perspective-correct and affine interpolation
+            if input.key_pressed(VirtualKeyCode::I) {
+                renderer.settings.interpolation = match renderer.settings.interpolation {
+                    Interpolation::PerspectiveCorrect => Interpolation::Affine,
+                    Interpolation::Affine => Interpolation::PerspectiveCorrect,
+                };
+            }
+
+            // Cycle between flat, Gouraud and per-pixel shading
+            if input.key_pressed(VirtualKeyCode::G) {
+                renderer.settings.shading = match renderer.settings.shading {
+                    Shading::Flat => Shading::Gouraud,
+                    Shading::Gouraud => Shading::Phong,
+                    Shading::Phong => Shading::Flat,
+                };
+            }
+
+            // Resize the window, along with the render target (skipped while minimized)
+            if let Some(size) = input
+                .window_resized()
+                .filter(|s| s.width > 0 && s.height > 0)
+            {
                 if let Err(err) = pixels.resize_surface(size.width, size.height) {
                     log_error("pixels.resize_surface", err);
                     *control_flow = ControlFlow::Exit;
                     return;
                 }
+                if let Err(err) = pixels.resize_buffer(size.width, size.height) {
+                    log_error("pixels.resize_buffer", err);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                if let Err(err) = renderer.resize(size.width, size.height) {
+                    log_error("renderer.resize", err);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+            }
+
+            // Toggle between the perspective and the orthographic projection
+            if input.key_pressed(VirtualKeyCode::P) {
+                renderer.camera.projection = match renderer.camera.projection {
+                    Projection::Perspective => Projection::Orthographic,
+                    Projection::Orthographic => Projection::Perspective,
+                };
+            }
+
+            // Standard front, side and top views
+            for (key, view) in [
+                (VirtualKeyCode::Key1, AxisView::Front),
+                (VirtualKeyCode::Key2, AxisView::Side),
+                (VirtualKeyCode::Key3, AxisView::Top),
+            ] {
+                if input.key_pressed(key) {
+                    renderer.set_axis_view(view);
+                }
+            }
+
+            // Save a screenshot of the last frame, along with its depth buffer when shift is held
+            if input.key_pressed(VirtualKeyCode::F12) {
+                match save_screenshot(&renderer.framebuffer, input.held_shift()) {
+                    Ok(files) => info!("Saved {}", files.join(", ")),
+                    Err(err) => log_error("save_screenshot", err),
+                }
             }
 
-            // Update internal state and request a redraw
-            renderer.update();
-            window.request_redraw();
+            // Switch between the fly and the orbit camera
+            if input.key_pressed(VirtualKeyCode::C) {
+                renderer.toggle_camera_mode();
+            }
+
+            // Move the camera by the real time elapsed since the last input, so that no mouse
+            // movement is lost between frames
+            let now = Instant::now();
+            renderer.update_camera(&input, now.duration_since(last_input).as_secs_f32());
+            last_input = now;
+
+            // When the next frame is due, catch the simulation up with real time and redraw
+            if frame_limiter
+                .as_mut()
+                .is_none_or(|limiter| limiter.frame_due(now))
+            {
+                let frame_dt = now.duration_since(last_frame).as_secs_f32();
+                last_frame = now;
+                for _ in 0..timestep.advance(frame_dt) {
+                    renderer.update(timestep.step);
+                }
+                window.request_redraw();
+            }
+
+            // Sleep until the next frame, unless something else happens first
+            if let Some(limiter) = &frame_limiter {
+                *control_flow = ControlFlow::WaitUntil(limiter.next_frame());
+            }
         }
     });
 }
 
+/// The F-22 flying over a ground plane, next to a few reference cubes. A small beacon cube
+/// circles around the aircraft as a child of its node, and spins on its own node. The sun and the
+/// sky light the whole scene, with a warm lamp and a blue spot on two corners of the ground.
+/// Returns the scene along with the spin of its animated nodes.
+fn load_demo_scene() -> Result<(Scene, Vec<(NodeId, Vec3)>), MeshLoadError> {
+    let aircraft = Arc::new(load_obj_file_data("assets/f22.obj".to_string())?);
+    let ground = Arc::new(load_obj_file_data("assets/ground.obj".to_string())?);
+    let cube = Arc::new(load_obj_file_data("assets/cube.obj".to_string())?);
+
+    let mut scene = Scene::default();
+
+    let aircraft_node = scene.add_node(None);
+    scene.node_mut(aircraft_node).translation.y = 1.5;
+    scene.attach(aircraft_node, aircraft);
+
+    // The beacon follows the aircraft, off its right wing
+    let beacon_node = scene.add_node(Some(aircraft_node));
+    scene.node_mut(beacon_node).translation = Vec3 {
+        x: 0.0,
+        y: 0.5,
+        z: -2.0,
+    };
+    scene.attach(beacon_node, Arc::clone(&cube)).scale = Vec3 {
+        x: 0.15,
+        y: 0.15,
+        z: 0.15,
+    };
+
+    let ground = scene.add(ground);
+    ground.scale = Vec3 {
+        x: 5.0,
+        y: 1.0,
+        z: 5.0,
+    };
+
+    // Reference cubes, resting on the ground
+    for (x, z) in [(-3.5, 3.5), (3.5, 3.5), (-3.5, -3.5), (3.5, -3.5)] {
+        let reference = scene.add(Arc::clone(&cube));
+        reference.scale = Vec3 {
+            x: 0.4,
+            y: 0.4,
+            z: 0.4,
+        };
+        reference.translation = Vec3 { x, y: 0.4, z };
+    }
+
+    // The sun, coming from above the camera so the ground is lit too, under a bluish sky
+    scene.lights.push(Light::directional(Vec3 {
+        x: 0.0,
+        y: -1.0,
+        z: 1.0,
+    }));
+    scene.lights.push(Light {
+        intensity: 0.35,
+        ..Light::hemisphere([0x90, 0xa8, 0xc8, 0xff], [0x40, 0x38, 0x30, 0xff])
+    });
+    scene.lights.push(Light::ambient(0.05));
+    scene.lights.push(Light {
+        color: [0xff, 0xa0, 0x40, 0xff],
+        range: 2.0,
+        ..Light::point(Vec3 {
+            x: 3.0,
+            y: 1.0,
+            z: -3.0,
+        })
+    });
+    scene.lights.push(Light {
+        color: [0x40, 0x80, 0xff, 0xff],
+        intensity: 2.0,
+        range: 4.0,
+        ..Light::spot(
+            Vec3 {
+                x: -3.5,
+                y: 3.0,
+                z: 3.5,
+            },
+            Vec3 {
+                x: 0.0,
+                y: -1.0,
+                z: 0.0,
+            },
+        )
+    });
+
+    let spinning_nodes = vec![
+        (
+            aircraft_node,
+            Vec3 {
+                x: 0.0,
+                y: 0.6,
+                z: 0.0,
+            },
+        ),
+        (
+            beacon_node,
+            Vec3 {
+                x: 3.0,
+                y: 0.0,
+                z: 0.0,
+            },
+        ),
+    ];
+
+    Ok((scene, spinning_nodes))
+}
+
 fn log_error<E: std::error::Error + 'static>(method_name: &str, err: E) {
     error!("{method_name}() failed: {err}");
     for source in err.sources().skip(1) {
@@ -1,17 +1,204 @@
-use crate::vector::Vec3;
+use std::f32::consts::FRAC_PI_6;
 
+use crate::consts::{C_BLACK, C_WHITE};
+use crate::material::Material;
+use crate::vector::{vec3_add, vec3_dot, vec3_length, vec3_mul, vec3_normalize, vec3_sub, Vec3};
+
+// ===================================================================
+// Variables & definitions
+// ===================================================================
+
+/// The shape of the light a `Light` casts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum LightKind {
+    /// Parallel rays along `direction`, from infinitely far away, like the sun.
+    #[default]
+    Directional,
+    /// Light in every direction from `position`, weakening with the distance.
+    Point,
+    /// A cone of light from `position` along `direction`, weakening with the distance.
+    Spot,
+    /// Light bounced around the scene, reaching every surface evenly from all sides.
+    Ambient,
+    /// Light from the sky above, with `color`, and bounced off the ground below, with
+    /// `ground_color`, blended by how much surfaces face up against `direction`.
+    Hemisphere,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Light {
+    pub kind: LightKind,
+    /// Direction the light travels in, for directional and spot lights, and from the sky to the
+    /// ground for hemisphere lights. Must be normalized.
     pub direction: Vec3,
+    /// Where point and spot lights shine from.
+    pub position: Vec3,
+    pub color: [u8; 4],
+    /// Color of the light coming from below, for hemisphere lights.
+    pub ground_color: [u8; 4],
+    /// Brightness multiplier of the color.
+    pub intensity: f32,
+    /// Distance at which point and spot lights have lost half of their intensity.
+    pub range: f32,
+    /// Angle between the axis and the edge of the cone of a spot light, in radians.
+    pub spot_angle: f32,
+    /// Fraction of the cone of a spot light, from its edge inwards, over which the light fades
+    /// out. 0.0 gives a hard edge.
+    pub spot_falloff: f32,
 }
 
-/// Mutates a color based on an intensity (which should be a percentage 0.0 - 1.0)
-pub fn light_apply_intensity(color: &[u8; 4], mut intensity: f32) -> [u8; 4] {
-    if intensity < 0.0 {
-        intensity = 0.0;
-    } else if intensity > 1.0 {
-        intensity = 1.0;
+impl Default for Light {
+    fn default() -> Self {
+        Light {
+            kind: LightKind::Directional,
+            direction: Vec3 {
+                x: 0.0,
+                y: -1.0,
+                z: 0.0,
+            },
+            position: Vec3 {
+                ..Default::default()
+            },
+            color: C_WHITE,
+            ground_color: C_BLACK,
+            intensity: 1.0,
+            range: 10.0,
+            spot_angle: FRAC_PI_6,
+            spot_falloff: 0.25,
+        }
+    }
+}
+
+// ===================================================================
+// Functions
+// ===================================================================
+
+impl Light {
+    /// A white directional light travelling along `direction`.
+    pub fn directional(mut direction: Vec3) -> Self {
+        vec3_normalize(&mut direction);
+        Light {
+            kind: LightKind::Directional,
+            direction,
+            ..Default::default()
+        }
+    }
+
+    /// A white point light at `position`.
+    pub fn point(position: Vec3) -> Self {
+        Light {
+            kind: LightKind::Point,
+            position,
+            ..Default::default()
+        }
     }
 
+    /// A white spot light at `position`, pointing along `direction`.
+    pub fn spot(position: Vec3, mut direction: Vec3) -> Self {
+        vec3_normalize(&mut direction);
+        Light {
+            kind: LightKind::Spot,
+            direction,
+            position,
+            ..Default::default()
+        }
+    }
+
+    /// A white ambient light.
+    pub fn ambient(intensity: f32) -> Self {
+        Light {
+            kind: LightKind::Ambient,
+            intensity,
+            ..Default::default()
+        }
+    }
+
+    /// A hemisphere light with a `sky_color` from above and a `ground_color` from below.
+    pub fn hemisphere(sky_color: [u8; 4], ground_color: [u8; 4]) -> Self {
+        Light {
+            kind: LightKind::Hemisphere,
+            color: sky_color,
+            ground_color,
+            ..Default::default()
+        }
+    }
+
+    /// Normalized direction from `point` towards the light, and how much of the light reaches
+    /// `point` once the distance and the spot cone are taken into account, for each of the red,
+    /// green and blue channels (1.0 being a full white light). `None` when no light gets there,
+    /// and for the ambient and hemisphere lights, which come from no single direction.
+    pub fn incoming(&self, point: &Vec3) -> Option<(Vec3, [f32; 3])> {
+        let mut to_light = match self.kind {
+            LightKind::Directional => vec3_mul(&self.direction, -1.0),
+            LightKind::Point | LightKind::Spot => vec3_sub(&self.position, point),
+            LightKind::Ambient | LightKind::Hemisphere => return None,
+        };
+        let distance = vec3_length(to_light);
+        if distance <= 0.0 {
+            return None;
+        }
+        vec3_normalize(&mut to_light);
+
+        let mut strength = self.intensity;
+        if self.kind != LightKind::Directional {
+            // Inverse square falloff, tamed so the light does not blow up up close
+            let ratio = distance / self.range;
+            strength /= 1.0 + ratio * ratio;
+        }
+        if self.kind == LightKind::Spot {
+            strength *= self.spot_factor(&to_light);
+        }
+        if strength <= 0.0 {
+            return None;
+        }
+
+        Some((to_light, color_to_light(&self.color, strength)))
+    }
+
+    /// How much of an ambient or hemisphere light reaches a surface facing `normal`
+    /// (normalized), for each of the red, green and blue channels. Lights that come from a
+    /// single direction give nothing here.
+    pub fn surrounding(&self, normal: &Vec3) -> [f32; 3] {
+        match self.kind {
+            LightKind::Ambient => color_to_light(&self.color, self.intensity),
+            LightKind::Hemisphere => {
+                // 1.0 facing straight at the sky, 0.0 facing straight at the ground
+                let sky = (1.0 - vec3_dot(normal, &self.direction)) / 2.0;
+                let sky_light = color_to_light(&self.color, self.intensity * sky);
+                let ground_light = color_to_light(&self.ground_color, self.intensity * (1.0 - sky));
+                [0, 1, 2].map(|i| sky_light[i] + ground_light[i])
+            }
+            LightKind::Directional | LightKind::Point | LightKind::Spot => [0.0; 3],
+        }
+    }
+
+    /// How much of a spot light shines towards `-to_light`: 1.0 inside the cone, fading to 0.0
+    /// over the falloff at its edge.
+    fn spot_factor(&self, to_light: &Vec3) -> f32 {
+        let cos_angle = -vec3_dot(to_light, &self.direction);
+        let cos_outer = self.spot_angle.cos();
+        let cos_inner = (self.spot_angle * (1.0 - self.spot_falloff.clamp(0.0, 1.0))).cos();
+
+        if cos_inner <= cos_outer {
+            return if cos_angle >= cos_outer { 1.0 } else { 0.0 };
+        }
+
+        // Smoothstep between the edge of the cone and where the falloff starts
+        let t = ((cos_angle - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+}
+
+/// The red, green and blue channels of a light `color`, scaled by `strength` (1.0 being a full
+/// white light).
+fn color_to_light(color: &[u8; 4], strength: f32) -> [f32; 3] {
+    [0, 1, 2].map(|i| color[i] as f32 / 255.0 * strength)
+}
+
+/// Mutates a color based on an intensity (which should be a percentage 0.0 - 1.0)
+pub fn light_apply_intensity(color: &[u8; 4], intensity: f32) -> [u8; 4] {
+    let intensity = intensity.clamp(0.0, 1.0);
+
     [
         (color[0] as f32 * intensity) as u8,
         (color[1] as f32 * intensity) as u8,
@@ -19,3 +206,91 @@ pub fn light_apply_intensity(color: &[u8; 4], mut intensity: f32) -> [u8; 4] {
         color[3],
     ]
 }
+
+/// Multiplies two colors component by component, as when a texel is tinted by a lit color
+pub fn color_multiply(a: &[u8; 4], b: &[u8; 4]) -> [u8; 4] {
+    [
+        (a[0] as u16 * b[0] as u16 / 255) as u8,
+        (a[1] as u16 * b[1] as u16 / 255) as u8,
+        (a[2] as u16 * b[2] as u16 / 255) as u8,
+        (a[3] as u16 * b[3] as u16 / 255) as u8,
+    ]
+}
+
+/// Light `color` with the diffuse light each channel receives, then add the specular light on
+/// top of it. Alpha is kept.
+fn light_combine(color: &[u8; 4], diffuse: &[f32; 3], specular: &[f32; 3]) -> [u8; 4] {
+    let channel =
+        |i: usize| (color[i] as f32 * diffuse[i] + 255.0 * specular[i]).clamp(0.0, 255.0) as u8;
+
+    [channel(0), channel(1), channel(2), color[3]]
+}
+
+/// Ambient and hemisphere light the surface of `material` reflects when facing `normal`.
+fn light_surrounding(lights: &[Light], material: &Material, normal: &Vec3) -> [f32; 3] {
+    let mut surrounding = [0.0; 3];
+    for light in lights.iter() {
+        let light_surrounding = light.surrounding(normal);
+        for i in 0..3 {
+            surrounding[i] += light_surrounding[i] * material.ambient[i] as f32 / 255.0;
+        }
+    }
+
+    surrounding
+}
+
+/// Light a point of a surface with the Lambert model: the ambient and hemisphere lights, plus
+/// the color of every other light scaled by the cosine of the angle between the `normal`
+/// (normalized) and the direction towards the light.
+pub fn light_lambert(
+    lights: &[Light],
+    material: &Material,
+    color: &[u8; 4],
+    point: &Vec3,
+    normal: &Vec3,
+) -> [u8; 4] {
+    let mut diffuse = light_surrounding(lights, material, normal);
+    for (to_light, incoming) in lights.iter().filter_map(|light| light.incoming(point)) {
+        let factor = vec3_dot(normal, &to_light).max(0.0);
+        for i in 0..3 {
+            diffuse[i] += incoming[i] * factor;
+        }
+    }
+
+    light_combine(color, &diffuse, &[0.0; 3])
+}
+
+/// Light a point of a surface with the Blinn-Phong model: the ambient and hemisphere lights, a
+/// diffuse term following the angle between the `normal` and each other light, and a specular
+/// highlight, tinted by the light and the material, where the normal is halfway between the
+/// light and the direction `to_eye` (pointing from the point to the camera). Both vectors must
+/// be normalized.
+pub fn light_blinn_phong(
+    lights: &[Light],
+    material: &Material,
+    color: &[u8; 4],
+    point: &Vec3,
+    normal: &Vec3,
+    to_eye: &Vec3,
+) -> [u8; 4] {
+    let mut diffuse = light_surrounding(lights, material, normal);
+    let mut specular = [0.0; 3];
+    for (to_light, incoming) in lights.iter().filter_map(|light| light.incoming(point)) {
+        let factor = vec3_dot(normal, &to_light).max(0.0);
+
+        // No highlight on the side facing away from the light
+        let mut highlight = 0.0;
+        let mut halfway = vec3_add(&to_light, to_eye);
+        if factor > 0.0 && vec3_length(halfway) > 0.0 {
+            vec3_normalize(&mut halfway);
+            highlight = vec3_dot(normal, &halfway).max(0.0).powf(material.shininess);
+        }
+
+        for i in 0..3 {
+            diffuse[i] += incoming[i] * factor;
+            specular[i] += incoming[i] * highlight * material.specular[i] as f32 / 255.0;
+        }
+    }
+
+    light_combine(color, &diffuse, &specular)
+}
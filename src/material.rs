@@ -0,0 +1,327 @@
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+use log::warn;
+
+use crate::consts::{C_BLACK, C_WHITE};
+use crate::mesh::{parse_number, MeshLoadError, ParseReason};
+use crate::texture::{load_png_texture, Texture};
+
+// ===================================================================
+// Variables & definitions
+// ===================================================================
+
+/// How the surface of a mesh reflects light, as described by a `newmtl` block of an .mtl file.
+#[derive(Clone, Debug)]
+pub struct Material {
+    /// Name the faces use to refer to the material (`usemtl`).
+    pub name: String,
+    /// Color of the surface (`Kd`), with its opacity (`d`) in alpha.
+    pub diffuse: [u8; 4],
+    /// Color of the ambient and hemisphere light the surface reflects (`Ka`).
+    pub ambient: [u8; 4],
+    /// Color of the specular highlights (`Ks`); black, the default, for a matte surface.
+    pub specular: [u8; 4],
+    /// Blinn-Phong exponent (`Ns`): the higher it is, the smaller and sharper the highlights.
+    pub shininess: f32,
+    /// Texture multiplied with the diffuse color (`map_Kd`).
+    pub texture: Option<Arc<Texture>>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            name: String::new(),
+            diffuse: C_WHITE,
+            ambient: C_WHITE,
+            specular: C_BLACK,
+            shininess: 32.0,
+            texture: None,
+        }
+    }
+}
+
+// ===================================================================
+// Functions
+// ===================================================================
+
+/// Read the materials of an .mtl file, in the order they are defined. Textures are looked up
+/// next to the .mtl file.
+pub fn load_mtl_file_data(filename: String) -> Result<Vec<Material>, MeshLoadError> {
+    let mut materials = Vec::new();
+
+    let io_error = |source| MeshLoadError::Io {
+        file: filename.clone(),
+        source,
+    };
+
+    let buff_reader = BufReader::new(fs::File::open(&filename).map_err(io_error)?);
+    let directory = Path::new(&filename).parent().unwrap_or(Path::new(""));
+
+    for (line_index, buf_line) in buff_reader.lines().enumerate() {
+        let line = buf_line.map_err(io_error)?;
+
+        let parse_error = |reason| MeshLoadError::Parse {
+            file: filename.clone(),
+            line: line_index + 1,
+            text: line.clone(),
+            reason,
+        };
+
+        let texture_file = match parse_mtl_line(&line, &mut materials) {
+            Ok(Some(texture_file)) => texture_file,
+            Ok(None) => continue,
+
+            // Spectral and CIE XYZ colors have no RGB equivalent the renderer could use
+            Err(ParseReason::UnsupportedDirective(directive)) => {
+                warn!(
+                    "{filename}:{}: skipping unsupported statement `{directive}`",
+                    line_index + 1
+                );
+                continue;
+            }
+
+            Err(reason) => return Err(parse_error(reason)),
+        };
+
+        // A texture that cannot be used leaves the material with its plain color, like the
+        // material libraries that are missing altogether
+        let texture_file = directory.join(texture_file).to_string_lossy().into_owned();
+        match load_png_texture(texture_file.clone()) {
+            Ok(texture) => {
+                if let Some(material) = materials.last_mut() {
+                    material.texture = Some(Arc::new(texture));
+                }
+            }
+            Err(err) => warn!("{filename}: could not load texture {texture_file}: {err}"),
+        }
+    }
+
+    Ok(materials)
+}
+
+/// Parse one color of an .mtl line: red, green and blue from 0.0 to 1.0. Green and blue default
+/// to red when left out. Colors given as a `spectral` curve or in CIE `xyz` are unsupported.
+fn parse_mtl_color<'a>(
+    directive: &str,
+    mut tokens: impl Iterator<Item = &'a str>,
+    alpha: u8,
+) -> Result<[u8; 4], ParseReason> {
+    let red: f32 = match tokens.next().ok_or(ParseReason::MissingValues)? {
+        form @ ("spectral" | "xyz") => {
+            return Err(ParseReason::UnsupportedDirective(format!(
+                "{directive} {form}"
+            )))
+        }
+        token => parse_number(token)?,
+    };
+    let green: f32 = match tokens.next() {
+        Some(token) => parse_number(token)?,
+        None => red,
+    };
+    let blue: f32 = match tokens.next() {
+        Some(token) => parse_number(token)?,
+        None => red,
+    };
+
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    Ok([channel(red), channel(green), channel(blue), alpha])
+}
+
+/// Parse the file name of a texture map statement, skipping the options that come before it
+/// (such as `-s 1 1 1` or `-clamp on`). The file name is everything after them, spaces included.
+fn parse_map_file<'a>(tokens: impl Iterator<Item = &'a str>) -> String {
+    let mut tokens = tokens.peekable();
+
+    while let Some(option) = tokens.next_if(|token| token.starts_with('-')) {
+        match option {
+            // offset, scale and turbulence take one to three numbers
+            "-o" | "-s" | "-t" => {
+                for _ in 0..3 {
+                    if tokens
+                        .next_if(|token| token.parse::<f32>().is_ok())
+                        .is_none()
+                    {
+                        break;
+                    }
+                }
+            }
+            "-mm" => {
+                tokens.next();
+                tokens.next();
+            }
+            // every other option takes a single value
+            _ => {
+                tokens.next();
+            }
+        }
+    }
+
+    tokens.collect::<Vec<_>>().join(" ")
+}
+
+/// Parse a single line of an .mtl file into the last of the materials being loaded. Returns the
+/// file named by a `map_Kd` statement, for the caller to load.
+fn parse_mtl_line(
+    line: &str,
+    materials: &mut Vec<Material>,
+) -> Result<Option<String>, ParseReason> {
+    let mut tokens = line.split_whitespace();
+
+    let directive = match tokens.next() {
+        // Blank lines and comments
+        None => return Ok(None),
+        Some(directive) if directive.starts_with('#') => return Ok(None),
+        Some(directive) => directive,
+    };
+
+    // start a new material
+    if directive == "newmtl" {
+        let name = tokens.collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            return Err(ParseReason::MissingValues);
+        }
+
+        materials.push(Material {
+            name,
+            ..Default::default()
+        });
+        return Ok(None);
+    }
+
+    let material = materials.last_mut().ok_or(ParseReason::MissingMaterial)?;
+    match directive {
+        "Kd" => material.diffuse = parse_mtl_color(directive, tokens, material.diffuse[3])?,
+        "Ka" => material.ambient = parse_mtl_color(directive, tokens, 0xff)?,
+        "Ks" => material.specular = parse_mtl_color(directive, tokens, 0xff)?,
+        "Ns" => {
+            material.shininess = parse_number(tokens.next().ok_or(ParseReason::MissingValues)?)?
+        }
+
+        // opacity, kept in the alpha of the diffuse color
+        "d" => {
+            let opacity: f32 = parse_number(tokens.next().ok_or(ParseReason::MissingValues)?)?;
+            material.diffuse[3] = (opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+
+        "map_Kd" => {
+            let texture_file = parse_map_file(tokens);
+            if texture_file.is_empty() {
+                return Err(ParseReason::MissingValues);
+            }
+            return Ok(Some(texture_file));
+        }
+
+        // .mtl files carry many statements the renderer has no use for (illumination models,
+        // refraction, bump maps...), so they are skipped rather than rejected
+        _ => {}
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse the lines of an .mtl file, returning the materials and the texture files named.
+    fn parse_lines(lines: &[&str]) -> (Vec<Material>, Vec<String>) {
+        let mut materials = Vec::new();
+        let mut texture_files = Vec::new();
+        for line in lines.iter() {
+            if let Some(texture_file) = parse_mtl_line(line, &mut materials).unwrap() {
+                texture_files.push(texture_file);
+            }
+        }
+        (materials, texture_files)
+    }
+
+    #[test]
+    fn materials_read_their_colors() {
+        let (materials, _) = parse_lines(&[
+            "# a comment",
+            "newmtl painted metal",
+            "Kd 1.0 0.5 0.0",
+            "Ka 0.2",
+            "Ks 0.5 0.5 0.5",
+            "Ns 64",
+            "d 0.5",
+            "illum 2",
+            "newmtl plain",
+        ]);
+
+        assert_eq!(materials.len(), 2);
+        let painted = &materials[0];
+        assert_eq!(painted.name, "painted metal");
+        assert_eq!(painted.diffuse, [0xff, 0x80, 0x00, 0x80]);
+        assert_eq!(painted.ambient, [0x33, 0x33, 0x33, 0xff]);
+        assert_eq!(painted.specular, [0x80, 0x80, 0x80, 0xff]);
+        assert_eq!(painted.shininess, 64.0);
+
+        // Anything left out keeps its default, matte white
+        assert_eq!(materials[1].diffuse, C_WHITE);
+        assert_eq!(materials[1].specular, C_BLACK);
+    }
+
+    #[test]
+    fn texture_files_come_after_the_map_options() {
+        let (_, texture_files) = parse_lines(&[
+            "newmtl textured",
+            "map_Kd livery.png",
+            "map_Kd -s 2 2 1 -clamp on -o 0.5 livery.png",
+            "map_Kd -mm 0 1 -s 2 my livery.png",
+        ]);
+
+        assert_eq!(texture_files, ["livery.png", "livery.png", "my livery.png"]);
+    }
+
+    #[test]
+    fn spectral_and_xyz_colors_are_unsupported() {
+        let mut materials = Vec::new();
+        parse_mtl_line("newmtl spectral", &mut materials).unwrap();
+
+        assert_eq!(
+            parse_mtl_line("Kd spectral red.rfl 1.0", &mut materials),
+            Err(ParseReason::UnsupportedDirective("Kd spectral".to_string()))
+        );
+        assert_eq!(
+            parse_mtl_line("Ks xyz 0.5", &mut materials),
+            Err(ParseReason::UnsupportedDirective("Ks xyz".to_string()))
+        );
+        assert_eq!(materials[0].diffuse, C_WHITE);
+    }
+
+    #[test]
+    fn malformed_statements_are_rejected() {
+        let mut materials = Vec::new();
+        assert_eq!(
+            parse_mtl_line("Kd 1 1 1", &mut materials),
+            Err(ParseReason::MissingMaterial)
+        );
+
+        parse_mtl_line("newmtl broken", &mut materials).unwrap();
+        assert_eq!(
+            parse_mtl_line("Kd red", &mut materials),
+            Err(ParseReason::BadNumber("red".to_string()))
+        );
+        assert_eq!(
+            parse_mtl_line("map_Kd -clamp on", &mut materials),
+            Err(ParseReason::MissingValues)
+        );
+    }
+
+    #[test]
+    fn unusable_textures_leave_the_material_untextured() {
+        let file = std::env::temp_dir().join(format!("frug3d-{}-textures.mtl", std::process::id()));
+        fs::write(&file, "newmtl missing\nKd 1 0 0\nmap_Kd missing.jpg\n").unwrap();
+
+        let materials = load_mtl_file_data(file.to_string_lossy().into_owned());
+        fs::remove_file(file).unwrap();
+
+        let materials = materials.unwrap();
+        assert!(materials[0].texture.is_none());
+        assert_eq!(materials[0].diffuse, [0xff, 0x00, 0x00, 0xff]);
+    }
+}
@@ -1,5 +1,6 @@
-use crate::vector::Vec4;
+use crate::vector::{vec3_cross, vec3_dot, vec3_normalize, vec3_sub, Vec3, Vec4};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Mat4 {
     pub m: [[f32; 4]; 4],
 }
@@ -75,7 +76,7 @@ pub fn mat4_make_rotation_x(angle: f32) -> Mat4 {
     m.m[2][1] = s;
     m.m[2][2] = c;
 
-    return m;
+    m
 }
 
 /// |  c 0 s 0 |
@@ -91,7 +92,7 @@ pub fn mat4_make_rotation_y(angle: f32) -> Mat4 {
     m.m[2][0] = -s;
     m.m[2][2] = c;
 
-    return m;
+    m
 }
 
 /// | c -s 0 0 |
@@ -107,7 +108,7 @@ pub fn mat4_make_rotation_z(angle: f32) -> Mat4 {
     m.m[1][0] = s;
     m.m[1][1] = c;
 
-    return m;
+    m
 }
 
 /// | (h/w)*1/tan(fov/2)        0               0               0           |
@@ -125,7 +126,54 @@ pub fn mat4_make_perspective(fov: f32, aspect: f32, znear: f32, zfar: f32) -> Ma
     m.m[2][3] = (-zfar * znear) / (zfar - znear);
     m.m[3][2] = 1.0;
 
-    return m;
+    m
+}
+
+/// Orthographic projection of a view volume `height` units tall, where `aspect` is the height of
+/// the viewport over its width. Depth maps to 0 at the near plane and 1 at the far plane, and
+/// w stays 1 so the perspective divide leaves points unchanged.
+///
+/// | (h/w)*2/height      0              0               0          |
+/// |       0         2/height           0               0          |
+/// |       0             0         1/(zf-zn)      -zn/(zf-zn)      |
+/// |       0             0              0               1          |
+pub fn mat4_make_orthographic(height: f32, aspect: f32, znear: f32, zfar: f32) -> Mat4 {
+    let mut m: Mat4 = Mat4 {
+        ..Default::default()
+    };
+
+    m.m[0][0] = aspect * (2.0 / height);
+    m.m[1][1] = 2.0 / height;
+    m.m[2][2] = 1.0 / (zfar - znear);
+    m.m[2][3] = -znear / (zfar - znear);
+    m.m[3][3] = 1.0;
+
+    m
+}
+
+/// View matrix of an eye looking at a target, for a left-handed system where the eye looks
+/// down +z once transformed.
+///
+/// | x.x  x.y  x.z  -dot(x, eye) |
+/// | y.x  y.y  y.z  -dot(y, eye) |
+/// | z.x  z.y  z.z  -dot(z, eye) |
+/// |  0    0    0        1       |
+pub fn mat4_look_at(eye: &Vec3, target: &Vec3, up: &Vec3) -> Mat4 {
+    // Forward (z), right (x) and up (y) axes of the eye
+    let mut z = vec3_sub(target, eye);
+    vec3_normalize(&mut z);
+    let mut x = vec3_cross(up, &z);
+    vec3_normalize(&mut x);
+    let y = vec3_cross(&z, &x);
+
+    Mat4 {
+        m: [
+            [x.x, x.y, x.z, -vec3_dot(&x, eye)],
+            [y.x, y.y, y.z, -vec3_dot(&y, eye)],
+            [z.x, z.y, z.z, -vec3_dot(&z, eye)],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    }
 }
 
 pub fn mat4_mul_vec4_project(mat_proj: &Mat4, v: &Vec4) -> Vec4 {
@@ -139,7 +187,7 @@ pub fn mat4_mul_vec4_project(mat_proj: &Mat4, v: &Vec4) -> Vec4 {
         result.z /= result.w;
     }
 
-    return result;
+    result
 }
 
 /// Multiplies a matrix (4d) with a Vec4, returning a Vec4
@@ -167,5 +215,61 @@ pub fn mat4_mul_mat4(a: &Mat4, b: &Mat4) -> Mat4 {
         }
     }
 
-    return mat;
+    mat
+}
+
+/// Matrix that applies a scale, then a rotation around x, y and z (in radians, in that order)
+/// and finally a translation.
+pub fn mat4_make_world(scale: &Vec3, rotation: &Vec3, translation: &Vec3) -> Mat4 {
+    let mut world_matrix = mat4_identity();
+
+    let scale_matrix = mat4_make_scale(scale.x, scale.y, scale.z);
+    let translation_matrix = mat4_make_translation(translation.x, translation.y, translation.z);
+    let rotation_x_matrix = mat4_make_rotation_x(rotation.x);
+    let rotation_y_matrix = mat4_make_rotation_y(rotation.y);
+    let rotation_z_matrix = mat4_make_rotation_z(rotation.z);
+
+    // Note: the order of these multiplications matters!! scale -> rotate -> translate
+    world_matrix = mat4_mul_mat4(&scale_matrix, &world_matrix);
+    world_matrix = mat4_mul_mat4(&rotation_x_matrix, &world_matrix);
+    world_matrix = mat4_mul_mat4(&rotation_y_matrix, &world_matrix);
+    world_matrix = mat4_mul_mat4(&rotation_z_matrix, &world_matrix);
+    world_matrix = mat4_mul_mat4(&translation_matrix, &world_matrix);
+
+    world_matrix
+}
+
+/// Matrix that moves normals the same way `m` moves points: the inverse transpose of its
+/// rotation and scale part. Unlike `m`, it keeps normals perpendicular to their surface under a
+/// non-uniform scale. Multiply normals with `w` = 0 and normalize them afterwards.
+pub fn mat4_normal_matrix(m: &Mat4) -> Mat4 {
+    let a = &m.m;
+
+    // The cofactors of the upper 3x3 are its inverse transpose, up to the determinant. The
+    // normals get normalized anyway, so only its sign matters, to keep them facing out.
+    let cofactor =
+        |r0: usize, r1: usize, c0: usize, c1: usize| a[r0][c0] * a[r1][c1] - a[r0][c1] * a[r1][c0];
+    let mut normal_matrix = mat4_identity();
+    normal_matrix.m[0][0] = cofactor(1, 2, 1, 2);
+    normal_matrix.m[0][1] = -cofactor(1, 2, 0, 2);
+    normal_matrix.m[0][2] = cofactor(1, 2, 0, 1);
+    normal_matrix.m[1][0] = -cofactor(0, 2, 1, 2);
+    normal_matrix.m[1][1] = cofactor(0, 2, 0, 2);
+    normal_matrix.m[1][2] = -cofactor(0, 2, 0, 1);
+    normal_matrix.m[2][0] = cofactor(0, 1, 1, 2);
+    normal_matrix.m[2][1] = -cofactor(0, 1, 0, 2);
+    normal_matrix.m[2][2] = cofactor(0, 1, 0, 1);
+
+    let determinant = a[0][0] * normal_matrix.m[0][0]
+        + a[0][1] * normal_matrix.m[0][1]
+        + a[0][2] * normal_matrix.m[0][2];
+    if determinant < 0.0 {
+        for row in normal_matrix.m.iter_mut().take(3) {
+            for value in row.iter_mut().take(3) {
+                *value = -*value;
+            }
+        }
+    }
+
+    normal_matrix
 }
@@ -1,18 +1,72 @@
+use std::fmt;
 use std::fs;
-use std::io::{BufRead, BufReader, Error};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
 
-use sscanf::scanf;
+use std::sync::Arc;
 
-use crate::consts::{self, C_BLUE, C_GREEN};
-use crate::C_WHITE;
+use log::warn;
+
+use crate::consts::C_WHITE;
+use crate::material::{load_mtl_file_data, Material};
+use crate::matrix::{mat4_make_world, mat4_mul_vec4, Mat4};
+use crate::texture::{Tex2, Texture};
+use crate::vector::{
+    vec3_add, vec3_cross, vec3_div, vec3_from_vec4, vec3_length, vec3_normalize, vec3_sub,
+    vec4_from_vec3,
+};
 use crate::{triangle::Face, vector::Vec3};
 
 // ===================================================================
 // Variables & definitions
 // ===================================================================
+
+/// Valid OBJ statements with nothing to render: lines, points, free-form curves and surfaces, and
+/// the display and rendering attributes that go with them. Exporters write them into otherwise
+/// ordinary meshes (Blender writes loose edges as `l`), so they are skipped rather than rejected.
+const SKIPPED_OBJ_STATEMENTS: [&str; 28] = [
+    "l",
+    "p",
+    "vp",
+    "cstype",
+    "deg",
+    "bmat",
+    "step",
+    "curv",
+    "curv2",
+    "surf",
+    "parm",
+    "trim",
+    "hole",
+    "scrv",
+    "sp",
+    "end",
+    "con",
+    "mg",
+    "bevel",
+    "c_interp",
+    "d_interp",
+    "lod",
+    "usemap",
+    "maplib",
+    "shadow_obj",
+    "trace_obj",
+    "ctech",
+    "stech",
+];
+
+#[derive(Clone, Debug)]
 pub struct Mesh {
     pub vertices: Vec<Vec3>,
+    pub texcoords: Vec<Tex2>,
+    /// Vertex normals, referenced by the `normal_indices` of the faces.
+    pub normals: Vec<Vec3>,
     pub faces: Vec<Face>,
+    /// Texture mapped onto the faces that have texture coordinates and whose material has no
+    /// texture of its own.
+    pub texture: Option<Arc<Texture>>,
+    /// Materials of the face groups, referenced by the `material` of the faces.
+    pub materials: Vec<Arc<Material>>,
     pub rotation: Vec3,
     pub scale: Vec3,
     pub translation: Vec3,
@@ -22,7 +76,11 @@ impl Default for Mesh {
     fn default() -> Self {
         Mesh {
             vertices: Vec::new(),
+            texcoords: Vec::new(),
+            normals: Vec::new(),
             faces: Vec::new(),
+            texture: None,
+            materials: Vec::new(),
             rotation: Vec3 {
                 ..Default::default()
             },
@@ -38,77 +96,580 @@ impl Default for Mesh {
     }
 }
 
+/// An axis-aligned box enclosing some geometry.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl BoundingBox {
+    pub fn center(&self) -> Vec3 {
+        vec3_div(&vec3_add(&self.min, &self.max), 2.0)
+    }
+
+    /// Radius of the sphere around the center that encloses the whole box.
+    pub fn radius(&self) -> f32 {
+        vec3_length(vec3_sub(&self.max, &self.min)) / 2.0
+    }
+
+    /// The smallest box enclosing both boxes.
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min: Vec3 {
+                x: self.min.x.min(other.min.x),
+                y: self.min.y.min(other.min.y),
+                z: self.min.z.min(other.min.z),
+            },
+            max: Vec3 {
+                x: self.max.x.max(other.max.x),
+                y: self.max.y.max(other.max.y),
+                z: self.max.z.max(other.max.z),
+            },
+        }
+    }
+}
+
+/// Why a line of an .obj file could not be loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseReason {
+    /// A token that should be a number is not one.
+    BadNumber(String),
+    /// A directive has fewer values than it needs.
+    MissingValues,
+    /// A face index is 0 or points past the elements defined so far.
+    IndexOutOfRange { index: i32, count: usize },
+    /// The directive is not one the loader understands.
+    UnsupportedDirective(String),
+    /// A material statement comes before any `newmtl`.
+    MissingMaterial,
+}
+
+impl fmt::Display for ParseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseReason::BadNumber(token) => write!(f, "`{token}` is not a valid number"),
+            ParseReason::MissingValues => write!(f, "not enough values"),
+            ParseReason::IndexOutOfRange { index, count } => {
+                write!(f, "index {index} is out of range (only {count} defined)")
+            }
+            ParseReason::UnsupportedDirective(directive) => {
+                write!(f, "unsupported directive `{directive}`")
+            }
+            ParseReason::MissingMaterial => write!(f, "no `newmtl` before this statement"),
+        }
+    }
+}
+
+/// Error returned when a mesh file cannot be loaded.
+#[derive(Debug)]
+pub enum MeshLoadError {
+    /// The file could not be opened or read.
+    Io {
+        file: String,
+        source: std::io::Error,
+    },
+    /// A line of the file is malformed. `line` is 1-based and `text` is the offending line.
+    Parse {
+        file: String,
+        line: usize,
+        text: String,
+        reason: ParseReason,
+    },
+}
+
+impl fmt::Display for MeshLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshLoadError::Io { file, source } => write!(f, "could not read {file}: {source}"),
+            MeshLoadError::Parse {
+                file,
+                line,
+                text,
+                reason,
+            } => write!(f, "{file}:{line}: {reason} in `{text}`"),
+        }
+    }
+}
+
+impl std::error::Error for MeshLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MeshLoadError::Io { source, .. } => Some(source),
+            MeshLoadError::Parse { .. } => None,
+        }
+    }
+}
+
 // ===================================================================
 // Functions
 // ===================================================================
 
-fn scan_str_to(original: String) {}
+/// Matrix that moves the mesh from model space into world space, applying its scale, rotation
+/// and translation.
+pub fn mesh_world_matrix(mesh: &Mesh) -> Mat4 {
+    mat4_make_world(&mesh.scale, &mesh.rotation, &mesh.translation)
+}
+
+/// World-space bounding box of the mesh vertices, or `None` for a mesh without vertices.
+pub fn mesh_bounding_box(mesh: &Mesh) -> Option<BoundingBox> {
+    vertices_bounding_box(&mesh.vertices, &mesh_world_matrix(mesh))
+}
+
+/// Bounding box of the vertices once moved by `matrix`, or `None` when there are no vertices.
+pub fn vertices_bounding_box(vertices: &[Vec3], matrix: &Mat4) -> Option<BoundingBox> {
+    vertices
+        .iter()
+        .map(|vertex| vec3_from_vec4(&mat4_mul_vec4(matrix, &vec4_from_vec3(vertex))))
+        .map(|p| BoundingBox { min: p, max: p })
+        .reduce(|a, b| a.union(&b))
+}
+
+/// Give a normal to every vertex of the faces that have none, averaging the normals of all the
+/// faces around the vertex. Larger faces weigh more, so that thin slivers don't skew the result.
+/// Normals already set on a face are kept.
+pub fn mesh_compute_vertex_normals(mesh: &mut Mesh) {
+    if mesh
+        .faces
+        .iter()
+        .all(|face| face.normal_indices.iter().all(Option::is_some))
+    {
+        return;
+    }
+
+    let mut vertex_normals = vec![
+        Vec3 {
+            ..Default::default()
+        };
+        mesh.vertices.len()
+    ];
+    for face in mesh.faces.iter() {
+        let a = mesh.vertices[(face.a - 1) as usize];
+        let b = mesh.vertices[(face.b - 1) as usize];
+        let c = mesh.vertices[(face.c - 1) as usize];
+
+        // Same orientation as the face normal of the pipeline; its length is twice the area
+        let face_normal = vec3_cross(&vec3_sub(&b, &a), &vec3_sub(&c, &a));
+        for index in [face.a, face.b, face.c] {
+            let normal = &mut vertex_normals[(index - 1) as usize];
+            *normal = vec3_add(normal, &face_normal);
+        }
+    }
+
+    // The computed normals go after the ones of the file, in vertex order
+    let first_index = mesh.normals.len() as i32 + 1;
+    for mut normal in vertex_normals {
+        if vec3_length(normal) > 0.0 {
+            vec3_normalize(&mut normal);
+        }
+        mesh.normals.push(normal);
+    }
+
+    for face in mesh.faces.iter_mut() {
+        let vertices = [face.a, face.b, face.c];
+        for (normal_index, vertex) in face.normal_indices.iter_mut().zip(vertices) {
+            normal_index.get_or_insert(first_index + vertex - 1);
+        }
+    }
+}
+
+/// Parse a single number of an .obj line.
+pub(crate) fn parse_number<T: std::str::FromStr>(token: &str) -> Result<T, ParseReason> {
+    token
+        .parse::<T>()
+        .map_err(|_| ParseReason::BadNumber(token.to_string()))
+}
+
+/// Resolve an OBJ index into a 1-based absolute index and check it is in bounds. Negative
+/// indices are relative to the `count` elements of that kind read so far (-1 being the last one).
+fn resolve_obj_index(index: i32, count: usize) -> Result<i32, ParseReason> {
+    let resolved = if index < 0 {
+        count as i32 + index + 1
+    } else {
+        index
+    };
+
+    if resolved < 1 || resolved as usize > count {
+        return Err(ParseReason::IndexOutOfRange { index, count });
+    }
+
+    Ok(resolved)
+}
+
+/// Parse one vertex reference of a face in any of the forms `v`, `v/vt`, `v//vn` or `v/vt/vn`,
+/// returning its resolved (vertex, texture, normal) indices.
+fn parse_face_vertex(
+    token: &str,
+    vertex_count: usize,
+    texcoord_count: usize,
+    normal_count: usize,
+) -> Result<(i32, Option<i32>, Option<i32>), ParseReason> {
+    let mut parts = token.split('/');
+
+    let vertex_index = resolve_obj_index(
+        parse_number(parts.next().unwrap_or_default())?,
+        vertex_count,
+    )?;
 
-/// Read contents of the .obj file and load them into mesh data
-pub fn load_obj_file_data(filename: String) -> Result<Mesh, Error> {
+    // An empty part (as in `v//vn`) means the index is not present
+    let mut parse_optional = |count: usize| match parts.next() {
+        Some(part) if !part.is_empty() => resolve_obj_index(parse_number(part)?, count).map(Some),
+        _ => Ok(None),
+    };
+    let texture_index = parse_optional(texcoord_count)?;
+    let normal_index = parse_optional(normal_count)?;
+
+    Ok((vertex_index, texture_index, normal_index))
+}
+
+/// Read contents of the .obj file and load them into mesh data. Material libraries (`mtllib`)
+/// are looked up next to the .obj file.
+pub fn load_obj_file_data(filename: String) -> Result<Mesh, MeshLoadError> {
     let mut mesh = Mesh {
         ..Default::default()
     };
 
-    let buff_reader = BufReader::new(fs::File::open(filename)?);
+    // Index of the material given to the faces, set by `usemtl`
+    let mut current_material: Option<usize> = None;
 
-    for buf_line in buff_reader.lines() {
-        let line = buf_line.expect("Error reading file line.");
+    let io_error = |source| MeshLoadError::Io {
+        file: filename.clone(),
+        source,
+    };
 
-        // get vertex data
-        if line.starts_with("v ") {
-            let mut vertex = Vec3 {
-                ..Default::default()
+    let buff_reader = BufReader::new(fs::File::open(&filename).map_err(io_error)?);
+    let directory = Path::new(&filename).parent().unwrap_or(Path::new(""));
+
+    for (line_index, buf_line) in buff_reader.lines().enumerate() {
+        let line = buf_line.map_err(io_error)?;
+
+        // Material libraries are files of their own, loaded as soon as they are named. OBJ
+        // files often get shared without them, so their faces are left white rather than
+        // failing to load.
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("mtllib") => {
+                for library in tokens {
+                    let library_file = directory.join(library).to_string_lossy().into_owned();
+                    match load_mtl_file_data(library_file) {
+                        Ok(materials) => mesh.materials.extend(materials.into_iter().map(Arc::new)),
+                        Err(MeshLoadError::Io { file, source })
+                            if source.kind() == std::io::ErrorKind::NotFound =>
+                        {
+                            warn!("{filename}: material library {file} not found");
+                        }
+                        Err(err) => return Err(err),
+                    }
+                }
+                continue;
+            }
+
+            // select the material of the faces that follow, the last one defined with that name
+            Some("usemtl") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                current_material = mesh
+                    .materials
+                    .iter()
+                    .rposition(|material| material.name == name);
+                if current_material.is_none() {
+                    warn!("{filename}:{}: unknown material `{name}`", line_index + 1);
+                }
+                continue;
+            }
+
+            Some(directive) if SKIPPED_OBJ_STATEMENTS.contains(&directive) => {
+                warn!(
+                    "{filename}:{}: skipping unsupported statement `{directive}`",
+                    line_index + 1
+                );
+                continue;
+            }
+
+            _ => {}
+        }
+
+        parse_obj_line(&line, &mut mesh, current_material).map_err(|reason| {
+            MeshLoadError::Parse {
+                file: filename.clone(),
+                line: line_index + 1,
+                text: line.clone(),
+                reason,
+            }
+        })?;
+    }
+
+    // Smooth shading needs a normal at every vertex, even when the file has none
+    mesh_compute_vertex_normals(&mut mesh);
+
+    Ok(mesh)
+}
+
+/// Parse a single line of an .obj file into the mesh being loaded. New faces get the
+/// `current_material`; material statements are handled by the caller.
+fn parse_obj_line(
+    line: &str,
+    mesh: &mut Mesh,
+    current_material: Option<usize>,
+) -> Result<(), ParseReason> {
+    let mut tokens = line.split_whitespace();
+
+    match tokens.next() {
+        // get vertex data (an optional w component is ignored)
+        Some("v") => {
+            let mut next_coord = || -> Result<f32, ParseReason> {
+                parse_number(tokens.next().ok_or(ParseReason::MissingValues)?)
+            };
+
+            let vertex = Vec3 {
+                x: next_coord()?,
+                y: next_coord()?,
+                z: next_coord()?,
             };
-            (vertex.x, vertex.y, vertex.z) = sscanf::scanf!(line, "v {} {} {}", f32, f32, f32)
-                .expect("Error reading vertex data.");
 
             mesh.vertices.push(vertex);
         }
 
-        // get face data
-        if line.starts_with("f ") {
-            // note: format is <vertex index>/<texture coords>/<normal indices>
-            let mut vertex_indices: [i32; 3] = [0; 3];
-            let mut texture_indices: [i32; 3] = [0; 3];
-            let mut normal_indices: [i32; 3] = [0; 3];
-
-            (
-                vertex_indices[0],
-                texture_indices[0],
-                normal_indices[0],
-                vertex_indices[1],
-                texture_indices[1],
-                normal_indices[1],
-                vertex_indices[2],
-                texture_indices[2],
-                normal_indices[2],
-            ) = sscanf::scanf!(
-                line,
-                "f {}/{}/{} {}/{}/{} {}/{}/{}",
-                i32,
-                i32,
-                i32,
-                i32,
-                i32,
-                i32,
-                i32,
-                i32,
-                i32
-            )
-            .expect("Error reading face data.");
-
-            let face = Face {
-                a: vertex_indices[0],
-                b: vertex_indices[1],
-                c: vertex_indices[2],
-                rgba: C_WHITE, // Hardcoded
+        // get texture coordinates (v defaults to 0, w is ignored)
+        Some("vt") => {
+            let u = parse_number(tokens.next().ok_or(ParseReason::MissingValues)?)?;
+            let v = match tokens.next() {
+                Some(token) => parse_number(token)?,
+                None => 0.0,
+            };
+
+            mesh.texcoords.push(Tex2 { u, v });
+        }
+
+        // get vertex normals
+        Some("vn") => {
+            let mut next_coord = || -> Result<f32, ParseReason> {
+                parse_number(tokens.next().ok_or(ParseReason::MissingValues)?)
             };
 
-            mesh.faces.push(face);
+            let mut normal = Vec3 {
+                x: next_coord()?,
+                y: next_coord()?,
+                z: next_coord()?,
+            };
+            if vec3_length(normal) > 0.0 {
+                vec3_normalize(&mut normal);
+            }
+
+            mesh.normals.push(normal);
+        }
+
+        // get face data
+        Some("f") => {
+            // note: each vertex is <vertex index>[/[<texture coords>][/<normal indices>]]
+            let polygon = tokens
+                .map(|token| {
+                    parse_face_vertex(
+                        token,
+                        mesh.vertices.len(),
+                        mesh.texcoords.len(),
+                        mesh.normals.len(),
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if polygon.len() < 3 {
+                return Err(ParseReason::MissingValues);
+            }
+
+            // Triangulate quads and n-gons as a fan around the first vertex
+            for i in 1..(polygon.len() - 1) {
+                let (a, b, c) = (polygon[0], polygon[i], polygon[i + 1]);
+
+                let face = Face {
+                    a: a.0,
+                    b: b.0,
+                    c: c.0,
+                    texture_indices: [a.1, b.1, c.1],
+                    normal_indices: [a.2, b.2, c.2],
+                    // Faces without a material are white
+                    rgba: current_material.map_or(C_WHITE, |index| mesh.materials[index].diffuse),
+                    material: current_material,
+                };
+
+                mesh.faces.push(face);
+            }
+        }
+
+        // Grouping statements don't change the geometry
+        Some("o" | "g" | "s") => {}
+
+        // Blank lines and comments
+        None => {}
+        Some(directive) if directive.starts_with('#') => {}
+
+        Some(directive) => return Err(ParseReason::UnsupportedDirective(directive.to_string())),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse the lines of an .obj file into a fresh mesh, without any material.
+    fn parse_lines(lines: &[&str]) -> Mesh {
+        let mut mesh = Mesh {
+            ..Default::default()
+        };
+        for line in lines.iter() {
+            parse_obj_line(line, &mut mesh, None).unwrap();
+        }
+        mesh
+    }
+
+    /// Write `contents` to a file of the temporary directory and return its path.
+    fn write_temp_file(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("frug3d-{}-{name}", std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn face_vertices(mesh: &Mesh) -> Vec<[i32; 3]> {
+        mesh.faces
+            .iter()
+            .map(|face| [face.a, face.b, face.c])
+            .collect()
+    }
+
+    #[test]
+    fn polygons_become_a_fan_of_triangles() {
+        let mesh = parse_lines(&[
+            "v 0 0 0",
+            "v 1 0 0",
+            "v 2 1 0",
+            "v 1 2 0",
+            "v 0 1 0",
+            "f 1 2 3 4 5",
+        ]);
+
+        // An n-gon becomes n - 2 triangles around its first vertex
+        assert_eq!(face_vertices(&mesh), vec![[1, 2, 3], [1, 3, 4], [1, 4, 5]]);
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_element() {
+        let mesh = parse_lines(&[
+            "v 0 0 0",
+            "v 1 0 0",
+            "v 1 1 0",
+            "f -3 -2 -1",
+            "v 0 1 0",
+            "vt 0 0",
+            "vt 1 1",
+            "f -4 -2/-2 -1/-1",
+        ]);
+
+        assert_eq!(face_vertices(&mesh), vec![[1, 2, 3], [1, 3, 4]]);
+        assert_eq!(mesh.faces[1].texture_indices, [None, Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn every_face_vertex_form_is_understood() {
+        let mesh = parse_lines(&[
+            "v 0 0 0",
+            "v 1 0 0",
+            "v 0 1 0",
+            "vt 0 0",
+            "vt 1 0",
+            "vt 0 1",
+            "vn 0 0 1",
+            "f 1 2 3",
+            "f 1/1 2/2 3/3",
+            "f 1//1 2//1 3//1",
+            "f 1/1/1 2/2/1 3/3/1",
+        ]);
+
+        let indices: Vec<_> = mesh
+            .faces
+            .iter()
+            .map(|face| (face.texture_indices, face.normal_indices))
+            .collect();
+        assert_eq!(
+            indices,
+            vec![
+                ([None; 3], [None; 3]),
+                ([Some(1), Some(2), Some(3)], [None; 3]),
+                ([None; 3], [Some(1); 3]),
+                ([Some(1), Some(2), Some(3)], [Some(1); 3]),
+            ]
+        );
+    }
+
+    #[test]
+    fn indices_out_of_range_are_rejected() {
+        let mut mesh = parse_lines(&["v 0 0 0", "v 1 0 0", "v 0 1 0"]);
+
+        for (line, index) in [("f 1 2 4", 4), ("f 0 1 2", 0), ("f -4 1 2", -4)] {
+            assert_eq!(
+                parse_obj_line(line, &mut mesh, None),
+                Err(ParseReason::IndexOutOfRange { index, count: 3 })
+            );
         }
-        //println!("faces len {}", mesh.faces.len());
+        assert!(mesh.faces.is_empty());
     }
 
-    return Ok(mesh);
+    #[test]
+    fn unsupported_directives_are_skipped() {
+        let file = write_temp_file(
+            "skipped.obj",
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nl 1 2\np 3\nvp 0.5\nf 1 2 3\n",
+        );
+
+        let mesh = load_obj_file_data(file.clone());
+        fs::remove_file(file).unwrap();
+        assert_eq!(face_vertices(&mesh.unwrap()), vec![[1, 2, 3]]);
+    }
+
+    #[test]
+    fn unknown_directives_are_rejected() {
+        let file = write_temp_file("typo.obj", "v 0 0 0\nv 1 0 0\nv 0 1 0\nfc 1 2 3\n");
+
+        let result = load_obj_file_data(file.clone());
+        fs::remove_file(&file).unwrap();
+        match result {
+            Err(MeshLoadError::Parse { line, reason, .. }) => {
+                assert_eq!(line, 4);
+                assert_eq!(reason, ParseReason::UnsupportedDirective("fc".to_string()));
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn malformed_lines_report_where_they_are() {
+        let file = write_temp_file("malformed.obj", "v 0 0 0\nv 1 zero 0\n");
+
+        let result = load_obj_file_data(file.clone());
+        fs::remove_file(&file).unwrap();
+        match result {
+            Err(MeshLoadError::Parse {
+                file: error_file,
+                line,
+                text,
+                reason,
+            }) => {
+                assert_eq!(error_file, file);
+                assert_eq!(line, 2);
+                assert_eq!(text, "v 1 zero 0");
+                assert_eq!(reason, ParseReason::BadNumber("zero".to_string()));
+            }
+            other => panic!("expected a parse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn missing_files_say_why_they_could_not_be_read() {
+        let err = load_obj_file_data("/nonexistent/mesh.obj".to_string()).unwrap_err();
+
+        assert!(matches!(err, MeshLoadError::Io { .. }));
+        let message = err.to_string();
+        assert!(message.starts_with("could not read /nonexistent/mesh.obj: "));
+        assert!(message.len() > "could not read /nonexistent/mesh.obj: ".len());
+    }
 }
@@ -0,0 +1,346 @@
+use std::sync::Arc;
+
+use crate::camera::{Camera, Projection};
+use crate::clipping::{clip_triangle, ClipVertex};
+use crate::display::{
+    draw_filled_triangle, draw_gouraud_triangle, draw_shaded_triangle, draw_textured_triangle,
+};
+use crate::framebuffer::Framebuffer;
+use crate::light::{color_multiply, light_blinn_phong, light_lambert, Light};
+use crate::material::Material;
+use crate::matrix::{mat4_mul_vec4, mat4_normal_matrix, Mat4};
+use crate::mesh::Mesh;
+use crate::texture::{texture_sample, Tex2};
+use crate::triangle::{interpolate_attribute, interpolate_vec3, Interpolation, Triangle};
+use crate::vector::{
+    vec3_add, vec3_cross, vec3_div, vec3_dot, vec3_from_vec4, vec3_length, vec3_mul,
+    vec3_normalize, vec3_sub, vec4_from_vec3, Vec3, Vec4,
+};
+
+/// How the pipeline decides which triangle is visible at each pixel.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum DepthMode {
+    /// Test every pixel against the per-pixel depth buffer.
+    #[default]
+    ZBuffer,
+    /// Sort triangles by their average depth and paint them back to front.
+    Painter,
+}
+
+/// How the lighting is spread across the faces.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Shading {
+    /// Light each face once with its face normal, so every face gets a single color.
+    #[default]
+    Flat,
+    /// Light each vertex with its vertex normal and blend the colors across the face, which
+    /// smooths out the facets of curved surfaces.
+    Gouraud,
+    /// Blend the vertex normals across the face and light every pixel with the Blinn-Phong
+    /// model of the face material, which adds specular highlights.
+    Phong,
+}
+
+/// Switches that select how triangles are rasterized.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub struct RenderSettings {
+    pub depth_mode: DepthMode,
+    pub interpolation: Interpolation,
+    pub shading: Shading,
+}
+
+/// Transform, cull, project and shade every face of a mesh as seen by the camera and lit by
+/// `lights`, pushing the resulting screen-space triangles for a `width` x `height` viewport into
+/// `triangles_to_render`. `world_matrix` places the mesh in the world, usually
+/// `mesh_world_matrix(mesh)`.
+#[allow(clippy::too_many_arguments)]
+pub fn project_mesh(
+    mesh: &Mesh,
+    world_matrix: &Mat4,
+    camera: &Camera,
+    lights: &[Light],
+    width: u32,
+    height: u32,
+    triangles_to_render: &mut Vec<Triangle>,
+) {
+    let view_matrix = camera.view_matrix();
+    let projection_matrix = camera.projection_matrix(width as f32 / height as f32);
+    let normal_matrix = mat4_normal_matrix(world_matrix);
+
+    // Faces without a material of their own share the default one
+    let default_material = Arc::new(Material {
+        ..Default::default()
+    });
+
+    // loop all triangle faces
+    for mesh_face in mesh.faces.iter() {
+        let face_vertices: [Vec3; 3] = [
+            mesh.vertices[(mesh_face.a - 1) as usize],
+            mesh.vertices[(mesh_face.b - 1) as usize],
+            mesh.vertices[(mesh_face.c - 1) as usize],
+        ];
+
+        // * loop all 3 vertices of this current face and apply transformations into world space *
+        let transformed_vertices: [Vec4; 3] =
+            face_vertices.map(|vertex| mat4_mul_vec4(world_matrix, &vec4_from_vec3(&vertex)));
+
+        // * Check backface culling *
+        let vec_a = vec3_from_vec4(&transformed_vertices[0]);
+        let vec_b = vec3_from_vec4(&transformed_vertices[1]);
+        let vec_c = vec3_from_vec4(&transformed_vertices[2]);
+
+        let mut vec_ab = vec3_sub(&vec_b, &vec_a); // B-A
+        let mut vec_ac = vec3_sub(&vec_c, &vec_a); // C-A
+        vec3_normalize(&mut vec_ab);
+        vec3_normalize(&mut vec_ac);
+
+        let mut normal = vec3_cross(&vec_ab, &vec_ac); // Use cross prod to find perpendicular.
+        vec3_normalize(&mut normal); // normalize normal vector
+
+        // Orthographic cameras look at every point along the same direction
+        let cam_ray = match camera.projection {
+            Projection::Perspective => vec3_sub(&camera.position, &vec_a),
+            Projection::Orthographic => vec3_mul(&camera.direction(), -1.0),
+        };
+
+        // Negative dot product -> not looking towards camera
+        let dot_normal_cam = vec3_dot(&normal, &cam_ray);
+
+        if dot_normal_cam < 0.0 {
+            // Bypass the triangles that are not looking at the camera
+            continue;
+        }
+
+        let material = match mesh_face.material {
+            Some(index) => &mesh.materials[index],
+            None => &default_material,
+        };
+
+        // Look up the texture coordinates when every vertex of the face has them, preferring
+        // the texture of the material over the one of the mesh
+        let mut texture = None;
+        let mut face_texcoords = [Tex2 {
+            ..Default::default()
+        }; 3];
+        if let (Some(face_texture), [Some(a_uv), Some(b_uv), Some(c_uv)]) = (
+            material.texture.as_ref().or(mesh.texture.as_ref()),
+            mesh_face.texture_indices,
+        ) {
+            face_texcoords = [
+                mesh.texcoords[(a_uv - 1) as usize],
+                mesh.texcoords[(b_uv - 1) as usize],
+                mesh.texcoords[(c_uv - 1) as usize],
+            ];
+            texture = Some(Arc::clone(face_texture));
+        }
+
+        // Calculate color based on the lights, for the whole face (at its center) and for each
+        // of its vertices
+        let face_center = vec3_div(&vec3_add(&vec3_add(&vec_a, &vec_b), &vec_c), 3.0);
+        let rgba = light_lambert(lights, material, &mesh_face.rgba, &face_center, &normal);
+
+        let vertex_normals: [Vec3; 3] = mesh_face.normal_indices.map(|normal_index| {
+            let Some(index) = normal_index else {
+                // Without a vertex normal, the face normal is the best guess
+                return normal;
+            };
+
+            let mesh_normal = vec4_from_vec3(&mesh.normals[(index - 1) as usize]);
+            let mut vertex_normal = vec3_from_vec4(&mat4_mul_vec4(
+                &normal_matrix,
+                &Vec4 {
+                    w: 0.0,
+                    ..mesh_normal
+                },
+            ));
+            if vec3_length(vertex_normal) > 0.0 {
+                vec3_normalize(&mut vertex_normal);
+            }
+            vertex_normal
+        });
+        let vertex_rgba: [[f32; 4]; 3] = [0, 1, 2].map(|j| {
+            let vertex = vec3_from_vec4(&transformed_vertices[j]);
+            light_lambert(
+                lights,
+                material,
+                &mesh_face.rgba,
+                &vertex,
+                &vertex_normals[j],
+            )
+            .map(f32::from)
+        });
+
+        // * Move the 3 vertices into view space, with the camera at the origin
+        let view_vertices: [Vec4; 3] =
+            transformed_vertices.map(|vertex| mat4_mul_vec4(&view_matrix, &vertex));
+
+        // * Project the 3 vertices into clip space and clip them against the frustum
+        let clip_vertices: [ClipVertex; 3] = [0, 1, 2].map(|j| ClipVertex {
+            position: mat4_mul_vec4(&projection_matrix, &view_vertices[j]),
+            texcoord: face_texcoords[j],
+            rgba: vertex_rgba[j],
+            world_position: vec3_from_vec4(&transformed_vertices[j]),
+            normal: vertex_normals[j],
+        });
+        let polygon = clip_triangle(&clip_vertices);
+        if polygon.is_empty() {
+            continue;
+        }
+
+        // * Perform the perspective divide and map the points into the view
+        let screen_points: Vec<Vec4> = polygon
+            .iter()
+            .map(|vertex| {
+                let mut projected_point = vertex.position;
+                projected_point.x /= projected_point.w;
+                projected_point.y /= projected_point.w;
+                projected_point.z /= projected_point.w;
+
+                // Scale into the view (flipping y, which grows downwards on screen)
+                projected_point.x *= width as f32 / 2.0;
+                projected_point.y *= -(height as f32 / 2.0);
+
+                // translate the projected points to the middle of the screen
+                projected_point.x += (width / 2) as f32;
+                projected_point.y += (height / 2) as f32;
+
+                projected_point
+            })
+            .collect();
+
+        // The average depth for each face is based on the vertices in view space.
+        let avg_depth = (view_vertices[0].z + view_vertices[1].z + view_vertices[2].z) / 3.0;
+
+        // save the clipped polygon as a fan of triangles to render
+        for i in 1..(polygon.len() - 1) {
+            let corners = [0, i, i + 1];
+            triangles_to_render.push(Triangle {
+                points: corners.map(|k| screen_points[k]),
+                texcoords: corners.map(|k| polygon[k].texcoord),
+                avg_depth,
+                rgba,
+                vertex_rgba: corners.map(|k| polygon[k].rgba.map(|channel| channel.round() as u8)),
+                base_rgba: mesh_face.rgba,
+                world_positions: corners.map(|k| polygon[k].world_position),
+                normals: corners.map(|k| polygon[k].normal),
+                material: Arc::clone(material),
+                texture: texture.clone(),
+            });
+        }
+    }
+}
+
+/// Color of the pixel of `triangle` with the given barycentric weights, lit by `lights` with
+/// the Blinn-Phong model.
+fn shade_pixel(
+    triangle: &Triangle,
+    weights: &Vec3,
+    camera: &Camera,
+    lights: &[Light],
+    interpolation: Interpolation,
+) -> [u8; 4] {
+    let points = &triangle.points;
+
+    // Blending normals shortens them, so they need normalizing again
+    let mut normal = interpolate_vec3(&triangle.normals, weights, points, interpolation);
+    if vec3_length(normal) > 0.0 {
+        vec3_normalize(&mut normal);
+    }
+
+    let position = interpolate_vec3(&triangle.world_positions, weights, points, interpolation);
+    let mut to_eye = match camera.projection {
+        Projection::Perspective => vec3_sub(&camera.position, &position),
+        Projection::Orthographic => vec3_mul(&camera.direction(), -1.0),
+    };
+    if vec3_length(to_eye) > 0.0 {
+        vec3_normalize(&mut to_eye);
+    }
+
+    let mut color = triangle.base_rgba;
+    if let Some(texture) = &triangle.texture {
+        let u = interpolate_attribute(
+            triangle.texcoords.map(|texcoord| texcoord.u),
+            weights,
+            points,
+            interpolation,
+        );
+        let v = interpolate_attribute(
+            triangle.texcoords.map(|texcoord| texcoord.v),
+            weights,
+            points,
+            interpolation,
+        );
+        color = color_multiply(&texture_sample(texture, u, v), &color);
+    }
+
+    light_blinn_phong(
+        lights,
+        &triangle.material,
+        &color,
+        &position,
+        &normal,
+        &to_eye,
+    )
+}
+
+/// Sort the triangles to render by their avg_depth, farthest first.
+pub fn sort_triangles_by_depth(triangles_to_render: &mut [Triangle]) {
+    triangles_to_render.sort_by(|a, b| b.avg_depth.total_cmp(&a.avg_depth));
+}
+
+/// Rasterize a list of projected triangles into the framebuffer. `camera` and `lights` must be
+/// the ones the triangles were projected with; `Shading::Phong` lights every pixel with them.
+///
+/// In `DepthMode::Painter` the triangles are expected to be sorted back to front already.
+pub fn draw_triangles(
+    framebuffer: &mut Framebuffer,
+    triangles_to_render: &[Triangle],
+    camera: &Camera,
+    lights: &[Light],
+    settings: &RenderSettings,
+) {
+    let depth_test = settings.depth_mode == DepthMode::ZBuffer;
+
+    for triangle in triangles_to_render.iter() {
+        // light every pixel of the face
+        if settings.shading == Shading::Phong {
+            draw_shaded_triangle(framebuffer, &triangle.points, depth_test, |weights| {
+                shade_pixel(triangle, weights, camera, lights, settings.interpolation)
+            });
+            continue;
+        }
+
+        let colors = match settings.shading {
+            Shading::Gouraud => triangle.vertex_rgba,
+            _ => [triangle.rgba; 3],
+        };
+
+        // draw textured faces
+        if let Some(texture) = &triangle.texture {
+            draw_textured_triangle(
+                framebuffer,
+                &triangle.points,
+                &triangle.texcoords,
+                texture,
+                &colors,
+                depth_test,
+                settings.interpolation,
+            );
+            continue;
+        }
+
+        // draw filled faces
+        match settings.shading {
+            Shading::Flat => {
+                draw_filled_triangle(framebuffer, &triangle.points, triangle.rgba, depth_test)
+            }
+            _ => draw_gouraud_triangle(
+                framebuffer,
+                &triangle.points,
+                &colors,
+                depth_test,
+                settings.interpolation,
+            ),
+        }
+    }
+}
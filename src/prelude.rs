@@ -0,0 +1,39 @@
+//! Re-exports of the types and functions most programs need to build and render a scene.
+//!
+//! ```no_run
+//! use frug3d::prelude::*;
+//! ```
+
+pub use crate::camera::{AxisView, Camera, Projection};
+pub use crate::consts::{
+    BACKGROUND_COLOR, C_BLACK, C_BLUE, C_GREEN, C_MAGENTA, C_RED, C_WHITE, FOV, ZFAR, ZNEAR,
+};
+pub use crate::display::{
+    clear_color_buffer, clear_depth_buffer, draw_filled_triangle, draw_gouraud_triangle, draw_grid,
+    draw_line, draw_pixel, draw_rect, draw_shaded_triangle, draw_textured_triangle, draw_triangle,
+    rasterize_triangle,
+};
+pub use crate::export::{save_png, save_ppm, save_screenshot, timestamped_filename};
+pub use crate::framebuffer::{Framebuffer, FramebufferSizeError};
+pub use crate::headless::HeadlessRenderer;
+pub use crate::light::{
+    color_multiply, light_apply_intensity, light_blinn_phong, light_lambert, Light, LightKind,
+};
+pub use crate::material::{load_mtl_file_data, Material};
+pub use crate::matrix::{
+    mat4_identity, mat4_look_at, mat4_make_orthographic, mat4_make_perspective,
+    mat4_make_rotation_x, mat4_make_rotation_y, mat4_make_rotation_z, mat4_make_scale,
+    mat4_make_translation, mat4_make_world, mat4_mul_mat4, mat4_mul_vec4, mat4_mul_vec4_project,
+    mat4_normal_matrix, Mat4,
+};
+pub use crate::mesh::{
+    load_obj_file_data, mesh_bounding_box, mesh_compute_vertex_normals, mesh_world_matrix,
+    vertices_bounding_box, BoundingBox, Mesh, MeshLoadError,
+};
+pub use crate::pipeline::{DepthMode, RenderSettings, Shading};
+pub use crate::recording::{frame_filename, record_turntable, Turntable, TurntableSpin};
+pub use crate::scene::{MeshInstance, NodeId, Scene, SceneNode};
+pub use crate::texture::{load_png_texture, texture_sample, Tex2, Texture};
+pub use crate::timing::FramePacing;
+pub use crate::triangle::{Face, Interpolation};
+pub use crate::vector::*;
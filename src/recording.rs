@@ -0,0 +1,130 @@
+use std::f32::consts::TAU;
+use std::path::Path;
+
+use crate::camera::Camera;
+use crate::export::save_ppm;
+use crate::headless::HeadlessRenderer;
+use crate::light::Light;
+use crate::scene::Scene;
+use crate::vector::{vec3_add, vec3_rotate_y, vec3_sub, Vec3};
+
+// ===================================================================
+// Variables & definitions
+// ===================================================================
+
+/// What turns around the vertical axis through the center of the scene during a turntable.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum TurntableSpin {
+    /// The model turns in front of the camera and the light, so every side gets lit in turn.
+    #[default]
+    Model,
+    /// The camera circles around the model, which stays lit from the same side.
+    Camera,
+}
+
+/// A full 360 degree turn recorded as a sequence of frames.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Turntable {
+    /// Number of frames of the whole turn. The last frame stops one step short of the first
+    /// one, so the sequence loops seamlessly.
+    pub frames: u32,
+    pub spin: TurntableSpin,
+}
+
+impl Default for Turntable {
+    fn default() -> Self {
+        Turntable {
+            frames: 120,
+            spin: TurntableSpin::Model,
+        }
+    }
+}
+
+// ===================================================================
+// Functions
+// ===================================================================
+
+impl Turntable {
+    /// Angle turned at `frame` (0-based), in radians. Every frame advances by the same fixed
+    /// step, however long the frames take to render.
+    pub fn angle(&self, frame: u32) -> f32 {
+        TAU * frame as f32 / self.frames as f32
+    }
+
+    /// Camera and lights that show `frame` (0-based) of the turn around `center`.
+    ///
+    /// Turning the model is the same as turning the camera and the lights the other way around
+    /// it, so the meshes of the scene are never modified.
+    pub fn frame_view(
+        &self,
+        frame: u32,
+        center: &Vec3,
+        camera: &Camera,
+        lights: &[Light],
+    ) -> (Camera, Vec<Light>) {
+        // Angle the camera turns around the center, in the same direction as a positive yaw
+        let angle = match self.spin {
+            TurntableSpin::Model => -self.angle(frame),
+            TurntableSpin::Camera => self.angle(frame),
+        };
+
+        // Note: vec3_rotate_y turns the opposite way of a positive yaw
+        let mut frame_camera = *camera;
+        let offset = vec3_sub(&camera.position, center);
+        frame_camera.position = vec3_add(center, &vec3_rotate_y(&offset, -angle));
+        frame_camera.yaw += angle;
+
+        let mut frame_lights = lights.to_vec();
+        if self.spin == TurntableSpin::Model {
+            for light in frame_lights.iter_mut() {
+                let offset = vec3_sub(&light.position, center);
+                light.position = vec3_add(center, &vec3_rotate_y(&offset, -angle));
+                light.direction = vec3_rotate_y(&light.direction, -angle);
+            }
+        }
+
+        (frame_camera, frame_lights)
+    }
+}
+
+/// Name of the image of `frame` (0-based) in a recorded sequence: `frame_0001.ppm` for the first
+/// one.
+pub fn frame_filename(frame: u32) -> String {
+    format!("frame_{:04}.ppm", frame + 1)
+}
+
+/// Render every frame of the turntable and write them as numbered .ppm files into `directory`,
+/// ready to be assembled by a video encoder. Returns the paths of the files written.
+pub fn record_turntable(
+    renderer: &mut HeadlessRenderer,
+    scene: &Scene,
+    camera: &Camera,
+    turntable: &Turntable,
+    directory: String,
+) -> std::io::Result<Vec<String>> {
+    let center = match scene.bounding_box() {
+        Some(bounds) => bounds.center(),
+        None => Vec3 {
+            ..Default::default()
+        },
+    };
+    let (width, height) = (renderer.framebuffer().width, renderer.framebuffer().height);
+
+    let mut frame_scene = scene.clone();
+    let mut files = Vec::new();
+    for frame in 0..turntable.frames {
+        let (frame_camera, frame_lights) =
+            turntable.frame_view(frame, &center, camera, &scene.lights);
+        frame_scene.lights = frame_lights;
+        let pixels = renderer.render_scene(&frame_scene, &frame_camera);
+
+        let file = Path::new(&directory)
+            .join(frame_filename(frame))
+            .to_string_lossy()
+            .into_owned();
+        save_ppm(file.clone(), width, height, &pixels)?;
+        files.push(file);
+    }
+
+    Ok(files)
+}
@@ -0,0 +1,300 @@
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::light::Light;
+use crate::matrix::{mat4_make_world, mat4_mul_mat4, Mat4};
+use crate::mesh::{vertices_bounding_box, BoundingBox, Mesh};
+use crate::pipeline::project_mesh;
+use crate::triangle::Triangle;
+use crate::vector::Vec3;
+
+// ===================================================================
+// Variables & definitions
+// ===================================================================
+
+/// Handle to a node of a `Scene`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// A node of the scene graph. Its transform is relative to its parent node, so moving a node
+/// moves all of its children and the meshes attached to them.
+#[derive(Clone, Debug)]
+pub struct SceneNode {
+    parent: Option<NodeId>,
+    pub rotation: Vec3,
+    pub scale: Vec3,
+    pub translation: Vec3,
+}
+
+/// One placement of a mesh in the scene. The geometry is shared between all the instances of
+/// the same mesh; only the transform belongs to the instance. The transform is relative to the
+/// node the instance is attached to, or to the world when `node` is `None`.
+#[derive(Clone, Debug)]
+pub struct MeshInstance {
+    pub mesh: Arc<Mesh>,
+    pub node: Option<NodeId>,
+    pub rotation: Vec3,
+    pub scale: Vec3,
+    pub translation: Vec3,
+}
+
+/// A tree of nodes with mesh instances attached, rendered together and lit by the same lights.
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+    /// Every node comes after its parent, so world matrices can be built in a single pass.
+    nodes: Vec<SceneNode>,
+    pub instances: Vec<MeshInstance>,
+    /// Lights shining on every instance, in world space.
+    pub lights: Vec<Light>,
+}
+
+// ===================================================================
+// Functions
+// ===================================================================
+
+impl SceneNode {
+    /// The node this one is attached to, or `None` for a root node.
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    /// Matrix that moves the node's space into its parent's space.
+    pub fn local_matrix(&self) -> Mat4 {
+        mat4_make_world(&self.scale, &self.rotation, &self.translation)
+    }
+}
+
+impl MeshInstance {
+    /// Create an instance of `mesh` at the root of the scene, starting from the transform stored
+    /// in the mesh itself.
+    pub fn new(mesh: Arc<Mesh>) -> Self {
+        MeshInstance {
+            node: None,
+            rotation: mesh.rotation,
+            scale: mesh.scale,
+            translation: mesh.translation,
+            mesh,
+        }
+    }
+
+    /// Matrix that moves the instance from model space into the space of its node.
+    pub fn local_matrix(&self) -> Mat4 {
+        mat4_make_world(&self.scale, &self.rotation, &self.translation)
+    }
+}
+
+impl Scene {
+    /// Add a node with an identity transform under `parent` (or at the root for `None`) and
+    /// return its handle.
+    ///
+    /// Panics if `parent` is not a node of this scene.
+    pub fn add_node(&mut self, parent: Option<NodeId>) -> NodeId {
+        if let Some(NodeId(index)) = parent {
+            assert!(index < self.nodes.len(), "parent node is not in the scene");
+        }
+
+        self.nodes.push(SceneNode {
+            parent,
+            rotation: Vec3 {
+                ..Default::default()
+            },
+            scale: Vec3 {
+                x: 1.0,
+                y: 1.0,
+                z: 1.0,
+            },
+            translation: Vec3 {
+                ..Default::default()
+            },
+        });
+
+        NodeId(self.nodes.len() - 1)
+    }
+
+    /// The node behind a handle returned by `add_node`.
+    pub fn node(&self, id: NodeId) -> &SceneNode {
+        &self.nodes[id.0]
+    }
+
+    /// The node behind a handle returned by `add_node`, to change its transform.
+    pub fn node_mut(&mut self, id: NodeId) -> &mut SceneNode {
+        &mut self.nodes[id.0]
+    }
+
+    /// Add an instance of `mesh` at the root of the scene and return it, so its transform can be
+    /// set.
+    pub fn add(&mut self, mesh: Arc<Mesh>) -> &mut MeshInstance {
+        self.instances.push(MeshInstance::new(mesh));
+        self.instances.last_mut().unwrap()
+    }
+
+    /// Add an instance of `mesh` attached to `node` and return it, so its transform (relative to
+    /// the node) can be set.
+    ///
+    /// Panics if `node` is not a node of this scene.
+    pub fn attach(&mut self, node: NodeId, mesh: Arc<Mesh>) -> &mut MeshInstance {
+        assert!(node.0 < self.nodes.len(), "node is not in the scene");
+
+        let instance = self.add(mesh);
+        instance.node = Some(node);
+        instance
+    }
+
+    /// World matrix of every node, indexed like the nodes: each local transform composed with
+    /// the world matrix of its parent.
+    pub fn node_world_matrices(&self) -> Vec<Mat4> {
+        let mut world_matrices: Vec<Mat4> = Vec::with_capacity(self.nodes.len());
+
+        // Parents always come first, so their world matrix is ready when a child needs it
+        for node in self.nodes.iter() {
+            let world_matrix = match node.parent {
+                Some(NodeId(parent)) => {
+                    mat4_mul_mat4(&world_matrices[parent], &node.local_matrix())
+                }
+                None => node.local_matrix(),
+            };
+            world_matrices.push(world_matrix);
+        }
+
+        world_matrices
+    }
+
+    /// World matrix of every instance, indexed like the instances.
+    pub fn instance_world_matrices(&self) -> Vec<Mat4> {
+        let node_world_matrices = self.node_world_matrices();
+
+        self.instances
+            .iter()
+            .map(|instance| match instance.node {
+                Some(NodeId(node)) => {
+                    mat4_mul_mat4(&node_world_matrices[node], &instance.local_matrix())
+                }
+                None => instance.local_matrix(),
+            })
+            .collect()
+    }
+
+    /// World-space bounding box of every instance, or `None` for an empty scene.
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.instances
+            .iter()
+            .zip(self.instance_world_matrices().iter())
+            .filter_map(|(instance, world_matrix)| {
+                vertices_bounding_box(&instance.mesh.vertices, world_matrix)
+            })
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Project every instance of the scene, as seen by the camera and lit by the lights of the
+    /// scene, into `triangles_to_render`.
+    pub(crate) fn project(
+        &self,
+        camera: &Camera,
+        width: u32,
+        height: u32,
+        triangles_to_render: &mut Vec<Triangle>,
+    ) {
+        let world_matrices = self.instance_world_matrices();
+        for (instance, world_matrix) in self.instances.iter().zip(world_matrices.iter()) {
+            project_mesh(
+                &instance.mesh,
+                world_matrix,
+                camera,
+                &self.lights,
+                width,
+                height,
+                triangles_to_render,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scene with a root node, a child node under it and an instance attached to the child, each
+    /// with its own transform.
+    fn nested_scene() -> (Scene, NodeId, NodeId) {
+        let mut scene = Scene::default();
+
+        let root = scene.add_node(None);
+        scene.node_mut(root).translation = Vec3 {
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+        };
+        let child = scene.add_node(Some(root));
+        scene.node_mut(child).rotation = Vec3 {
+            x: 0.0,
+            y: 0.5,
+            z: 0.0,
+        };
+        scene.node_mut(child).translation = Vec3 {
+            x: 0.0,
+            y: 0.0,
+            z: 4.0,
+        };
+
+        let instance = scene.attach(
+            child,
+            Arc::new(Mesh {
+                ..Default::default()
+            }),
+        );
+        instance.scale = Vec3 {
+            x: 2.0,
+            y: 2.0,
+            z: 2.0,
+        };
+        instance.translation = Vec3 {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        };
+
+        (scene, root, child)
+    }
+
+    #[test]
+    fn instance_world_matrix_composes_its_nodes() {
+        let (scene, root, child) = nested_scene();
+
+        let node_world = mat4_mul_mat4(
+            &scene.node(root).local_matrix(),
+            &scene.node(child).local_matrix(),
+        );
+        let expected = mat4_mul_mat4(&node_world, &scene.instances[0].local_matrix());
+        assert_eq!(scene.instance_world_matrices()[0], expected);
+    }
+
+    #[test]
+    fn moving_a_parent_moves_its_children() {
+        let (mut scene, root, _) = nested_scene();
+        let before = scene.instance_world_matrices()[0];
+
+        scene.node_mut(root).translation.x += 2.0;
+        let after = scene.instance_world_matrices()[0];
+
+        for row in 0..4 {
+            let expected = before.m[row][3] + if row == 0 { 2.0 } else { 0.0 };
+            assert!((after.m[row][3] - expected).abs() < 1e-5);
+            assert_eq!(after.m[row][..3], before.m[row][..3]);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "node is not in the scene")]
+    fn attaching_to_a_foreign_node_panics() {
+        let mut other = Scene::default();
+        let node = other.add_node(None);
+
+        let mut scene = Scene::default();
+        scene.attach(
+            node,
+            Arc::new(Mesh {
+                ..Default::default()
+            }),
+        );
+    }
+}
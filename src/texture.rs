@@ -0,0 +1,139 @@
+use std::fs;
+use std::io::BufReader;
+
+use crate::consts::C_MAGENTA;
+use crate::framebuffer::buffer_lengths;
+
+/// A texture coordinate. `u` grows to the right and `v` grows upwards, as in OBJ files.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tex2 {
+    pub u: f32,
+    pub v: f32,
+}
+
+impl Default for Tex2 {
+    fn default() -> Self {
+        Tex2 { u: 0.0, v: 0.0 }
+    }
+}
+
+/// An in-memory RGBA texture, stored row by row starting at the top-left texel.
+#[derive(Clone, Debug)]
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl Texture {
+    /// Create a texture from raw RGBA bytes. `data` must hold `width * height * 4` bytes.
+    pub fn from_rgba(width: u32, height: u32, data: Vec<u8>) -> Self {
+        let bytes = buffer_lengths(width, height).map(|(_, bytes)| bytes);
+        assert_eq!(Ok(data.len()), bytes, "{width}x{height} texture");
+        Texture {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+/// Sample the texel at coordinate (u, v) with nearest filtering. Coordinates outside 0.0 - 1.0
+/// wrap around, so the texture repeats.
+pub fn texture_sample(texture: &Texture, u: f32, v: f32) -> [u8; 4] {
+    if texture.width == 0 || texture.height == 0 {
+        return C_MAGENTA;
+    }
+
+    // Flip v, since the texture is stored from the top row down. Round down rather than towards
+    // zero, so negative coordinates land on the texel they are in.
+    let x = (u * texture.width as f32).floor() as i64;
+    let y = ((1.0 - v) * texture.height as f32).floor() as i64;
+    let x = x.rem_euclid(texture.width as i64) as usize;
+    let y = y.rem_euclid(texture.height as i64) as usize;
+
+    let index = (y * texture.width as usize + x) * 4;
+    [
+        texture.data[index],
+        texture.data[index + 1],
+        texture.data[index + 2],
+        texture.data[index + 3],
+    ]
+}
+
+/// Decode a .png file into an RGBA texture.
+pub fn load_png_texture(filename: String) -> Result<Texture, png::DecodingError> {
+    let mut decoder = png::Decoder::new(BufReader::new(fs::File::open(filename)?));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let pixels = &buffer[..info.buffer_size()];
+
+    // Expand every pixel to RGBA
+    let data = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 0xff])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        // Indexed images are expanded to RGB(A) by the decoder transformations
+        png::ColorType::Grayscale | png::ColorType::Indexed => {
+            pixels.iter().flat_map(|&p| [p, p, p, 0xff]).collect()
+        }
+    };
+
+    Ok(Texture::from_rgba(info.width, info.height, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4x1 texture whose texels have red values 0, 1, 2 and 3 from left to right.
+    fn texel_row() -> Texture {
+        let data = (0..4).flat_map(|x| [x, 0, 0, 0xff]).collect();
+        Texture::from_rgba(4, 1, data)
+    }
+
+    fn sampled_column(texture: &Texture, u: f32) -> u8 {
+        texture_sample(texture, u, 0.5)[0]
+    }
+
+    #[test]
+    fn coordinates_pick_the_texel_they_fall_in() {
+        let texture = texel_row();
+
+        assert_eq!(sampled_column(&texture, 0.0), 0);
+        assert_eq!(sampled_column(&texture, 0.3), 1);
+        assert_eq!(sampled_column(&texture, 0.99), 3);
+    }
+
+    #[test]
+    fn coordinates_outside_the_texture_wrap_around() {
+        let texture = texel_row();
+
+        assert_eq!(sampled_column(&texture, 1.0), 0);
+        assert_eq!(sampled_column(&texture, 1.3), 1);
+        assert_eq!(sampled_column(&texture, -0.1), 3);
+        assert_eq!(sampled_column(&texture, -0.3), 2);
+        assert_eq!(sampled_column(&texture, -0.8), 0);
+        assert_eq!(sampled_column(&texture, -1.1), 3);
+    }
+
+    #[test]
+    fn v_grows_upwards() {
+        let data = [[0xff, 0, 0, 0xff], [0, 0xff, 0, 0xff]].concat();
+        let texture = Texture::from_rgba(1, 2, data);
+
+        // The first row is the top of the texture
+        assert_eq!(texture_sample(&texture, 0.5, 0.75), [0xff, 0, 0, 0xff]);
+        assert_eq!(texture_sample(&texture, 0.5, 0.25), [0, 0xff, 0, 0xff]);
+        assert_eq!(texture_sample(&texture, 0.5, -0.25), [0xff, 0, 0, 0xff]);
+    }
+}
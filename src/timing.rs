@@ -0,0 +1,170 @@
+use std::time::{Duration, Instant};
+
+use crate::consts::{FPS, FRAME_TARGET_TIME};
+
+// ===================================================================
+// Variables & definitions
+// ===================================================================
+
+/// Longest real time a single frame can add to the simulation, in seconds. After a long stall
+/// (a window being dragged, a breakpoint) the simulation slows down instead of running a huge
+/// number of steps to catch up.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// How the viewer paces the frames it draws.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FramePacing {
+    /// Draw at most this many frames per second, waiting in between.
+    TargetFps(u32),
+    /// Let the vertical sync of the display pace the frames.
+    VSync,
+    /// Draw frames as fast as possible.
+    Unlimited,
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        FramePacing::TargetFps(FPS)
+    }
+}
+
+/// Turns real elapsed time into a whole number of fixed simulation steps, so that animations run
+/// at the same speed whatever the frame rate. The time left over is kept for the next frame, and
+/// tells how far to interpolate between the last two steps.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FixedTimestep {
+    /// Length of a simulation step, in seconds.
+    pub step: f32,
+    accumulator: f32,
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        FixedTimestep::new(FRAME_TARGET_TIME / 1000.0)
+    }
+}
+
+/// Keeps frames at a steady rate by telling when the next one is due.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FrameLimiter {
+    frame_time: Duration,
+    next_frame: Instant,
+}
+
+// ===================================================================
+// Functions
+// ===================================================================
+
+impl FixedTimestep {
+    /// Create a timestep of `step` seconds.
+    pub fn new(step: f32) -> Self {
+        assert!(step > 0.0);
+        FixedTimestep {
+            step,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Add `dt` seconds of real time and return how many steps the simulation must advance.
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt.min(MAX_FRAME_TIME);
+
+        let steps = (self.accumulator / self.step) as u32;
+        self.accumulator -= steps as f32 * self.step;
+
+        steps
+    }
+
+    /// How far the current time is from the last step towards the next one, between 0.0 and
+    /// 1.0. Use it to interpolate the state of the last two steps when drawing.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+impl FrameLimiter {
+    /// Create a limiter for `fps` frames per second, with the first frame due right away.
+    pub fn new(fps: u32) -> Self {
+        FrameLimiter {
+            frame_time: Duration::from_secs_f32(1.0 / fps.max(1) as f32),
+            next_frame: Instant::now(),
+        }
+    }
+
+    /// When the next frame is due.
+    pub fn next_frame(&self) -> Instant {
+        self.next_frame
+    }
+
+    /// Whether the next frame is due at `now`. When it is, the frame after it gets scheduled.
+    pub fn frame_due(&mut self, now: Instant) -> bool {
+        if now < self.next_frame {
+            return false;
+        }
+
+        // Keep a steady rhythm, unless we fell behind by more than a frame
+        self.next_frame += self.frame_time;
+        if self.next_frame < now {
+            self.next_frame = now + self.frame_time;
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leftover_time_carries_over_to_the_next_frame() {
+        let mut timestep = FixedTimestep::new(0.125);
+
+        assert_eq!(timestep.advance(0.1), 0);
+        assert!((timestep.alpha() - 0.8).abs() < 1e-5);
+
+        // 0.1 + 0.1 = 1.6 steps: one now, the rest is kept
+        assert_eq!(timestep.advance(0.1), 1);
+        assert!((timestep.alpha() - 0.6).abs() < 1e-5);
+
+        assert_eq!(timestep.advance(0.05), 1);
+        assert!(timestep.alpha().abs() < 1e-5);
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let mut timestep = FixedTimestep::new(0.125);
+
+        assert_eq!(timestep.advance(10.0), (MAX_FRAME_TIME / 0.125) as u32);
+        assert!(timestep.alpha().abs() < 1e-5);
+    }
+
+    #[test]
+    fn frames_keep_a_steady_rhythm() {
+        let mut limiter = FrameLimiter::new(10);
+        let frame_time = limiter.frame_time;
+        let start = limiter.next_frame();
+
+        assert!(limiter.frame_due(start));
+        assert_eq!(limiter.next_frame(), start + frame_time);
+
+        // Too early: nothing changes
+        assert!(!limiter.frame_due(start + frame_time / 2));
+        assert_eq!(limiter.next_frame(), start + frame_time);
+
+        // A bit late: the next frame stays on the original rhythm
+        assert!(limiter.frame_due(start + frame_time * 6 / 5));
+        assert_eq!(limiter.next_frame(), start + frame_time * 2);
+    }
+
+    #[test]
+    fn frames_reschedule_after_falling_behind() {
+        let mut limiter = FrameLimiter::new(10);
+        let frame_time = limiter.frame_time;
+        let start = limiter.next_frame();
+
+        let late = start + frame_time * 5;
+        assert!(limiter.frame_due(late));
+        assert_eq!(limiter.next_frame(), late + frame_time);
+    }
+}
@@ -1,35 +1,156 @@
-use crate::{consts::C_MAGENTA, vector::Vec2};
+use std::sync::Arc;
 
+use crate::{
+    consts::C_MAGENTA,
+    material::Material,
+    texture::{Tex2, Texture},
+    vector::{Vec3, Vec4},
+};
+
+/// A triangular face of a mesh. All indices are 1-based, as in the OBJ file.
+#[derive(Copy, Clone, Debug)]
 pub struct Face {
     pub a: i32,
     pub b: i32,
     pub c: i32,
+    /// Texture coordinate index of each vertex, if the file provided one.
+    pub texture_indices: [Option<i32>; 3],
+    /// Normal index of each vertex, if the file provided one.
+    pub normal_indices: [Option<i32>; 3],
+    /// Color of the face: the diffuse color of its material, or white.
     pub rgba: [u8; 4],
+    /// Index of the material of the face among the materials of its mesh, if it has one.
+    pub material: Option<usize>,
+}
+
+/// How per-vertex attributes are interpolated across the pixels of a triangle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// Interpolate attribute/w and 1/w in screen space and divide them back, which follows
+    /// the perspective of the triangle.
+    #[default]
+    PerspectiveCorrect,
+    /// Interpolate attributes linearly in screen space. Cheaper, but textures visibly warp on
+    /// large polygons close to the camera.
+    Affine,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Debug)]
 pub struct Triangle {
-    pub points: [Vec2; 3],
+    /// Screen-space points; `z` keeps the projected depth and `w` the view-space depth.
+    pub points: [Vec4; 3],
+    pub texcoords: [Tex2; 3],
     pub avg_depth: f32,
+    /// Color of the whole face, lit once with the face normal.
     pub rgba: [u8; 4],
+    /// Color of each point, lit with the vertex normals, for smooth shading.
+    pub vertex_rgba: [[u8; 4]; 3],
+    /// Color of the face before lighting, for per-pixel lighting.
+    pub base_rgba: [u8; 4],
+    /// World-space position and normal of each point, for per-pixel lighting.
+    pub world_positions: [Vec3; 3],
+    pub normals: [Vec3; 3],
+    pub material: Arc<Material>,
+    /// Texture to map with `texcoords`; untextured triangles are filled with `rgba`.
+    pub texture: Option<Arc<Texture>>,
 }
 
 impl Default for Triangle {
     fn default() -> Self {
         Triangle {
             points: [
-                Vec2 {
+                Vec4 {
                     ..Default::default()
                 },
-                Vec2 {
+                Vec4 {
                     ..Default::default()
                 },
-                Vec2 {
+                Vec4 {
                     ..Default::default()
                 },
             ],
+            texcoords: [Tex2 {
+                ..Default::default()
+            }; 3],
             avg_depth: 0.0,
             rgba: C_MAGENTA,
+            vertex_rgba: [C_MAGENTA; 3],
+            base_rgba: C_MAGENTA,
+            world_positions: [Vec3 {
+                ..Default::default()
+            }; 3],
+            normals: [Vec3 {
+                ..Default::default()
+            }; 3],
+            material: Arc::new(Material {
+                ..Default::default()
+            }),
+            texture: None,
+        }
+    }
+}
+
+/// Interpolate the color of each point of the triangle at the pixel with the given barycentric
+/// weights, channel by channel.
+pub fn interpolate_color(
+    colors: &[[u8; 4]; 3],
+    weights: &Vec3,
+    points: &[Vec4; 3],
+    interpolation: Interpolation,
+) -> [u8; 4] {
+    // Flat colored triangles are common enough to skip the work
+    if colors[0] == colors[1] && colors[1] == colors[2] {
+        return colors[0];
+    }
+
+    [0, 1, 2, 3].map(|channel| {
+        let value = interpolate_attribute(
+            colors.map(|color| color[channel] as f32),
+            weights,
+            points,
+            interpolation,
+        );
+        value.round().clamp(0.0, 255.0) as u8
+    })
+}
+
+/// Interpolate a per-vertex vector at the pixel with the given barycentric weights, component
+/// by component.
+pub fn interpolate_vec3(
+    values: &[Vec3; 3],
+    weights: &Vec3,
+    points: &[Vec4; 3],
+    interpolation: Interpolation,
+) -> Vec3 {
+    Vec3 {
+        x: interpolate_attribute(values.map(|v| v.x), weights, points, interpolation),
+        y: interpolate_attribute(values.map(|v| v.y), weights, points, interpolation),
+        z: interpolate_attribute(values.map(|v| v.z), weights, points, interpolation),
+    }
+}
+
+/// Interpolate a per-vertex attribute (one value for each point of the triangle) at the pixel
+/// with the given barycentric weights. Points keep their view-space depth in `w`, which is what
+/// perspective-correct interpolation divides by.
+pub fn interpolate_attribute(
+    values: [f32; 3],
+    weights: &Vec3,
+    points: &[Vec4; 3],
+    interpolation: Interpolation,
+) -> f32 {
+    match interpolation {
+        Interpolation::Affine => {
+            weights.x * values[0] + weights.y * values[1] + weights.z * values[2]
+        }
+        Interpolation::PerspectiveCorrect => {
+            // attribute/w and 1/w are linear in screen space, the attribute itself is not
+            let value_over_w = weights.x * values[0] / points[0].w
+                + weights.y * values[1] / points[1].w
+                + weights.z * values[2] / points[2].w;
+            let reciprocal_w =
+                weights.x / points[0].w + weights.y / points[1].w + weights.z / points[2].w;
+
+            value_over_w / reciprocal_w
         }
     }
 }
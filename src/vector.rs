@@ -1,4 +1,4 @@
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vec2 {
     pub x: f32,
     pub y: f32,
@@ -10,7 +10,7 @@ impl Default for Vec2 {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vec3 {
     pub x: f32,
     pub y: f32,
@@ -27,6 +27,7 @@ impl Default for Vec3 {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Vec4 {
     pub x: f32,
     pub y: f32,
@@ -49,7 +50,7 @@ impl Default for Vec4 {
 // Vector 2D functions
 // ===============================================================================
 pub fn vec2_length(v: Vec2) -> f32 {
-    return (v.x * v.x + v.y * v.y).sqrt();
+    (v.x * v.x + v.y * v.y).sqrt()
 }
 
 pub fn vec2_add(a: Vec2, b: Vec2) -> Vec2 {
@@ -81,7 +82,7 @@ pub fn vec2_div(v: Vec2, factor: f32) -> Vec2 {
 }
 
 pub fn vec2_dot(a: Vec2, b: Vec2) -> f32 {
-    return (a.x * b.x) + (a.y * b.y);
+    (a.x * b.x) + (a.y * b.y)
 }
 
 pub fn vec2_normalize(v: &mut Vec2) {
@@ -94,7 +95,7 @@ pub fn vec2_normalize(v: &mut Vec2) {
 // Vector 3D functions
 // ===============================================================================
 pub fn vec3_length(v: Vec3) -> f32 {
-    return (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
+    (v.x * v.x + v.y * v.y + v.z * v.z).sqrt()
 }
 
 pub fn vec3_add(a: &Vec3, b: &Vec3) -> Vec3 {
@@ -138,7 +139,7 @@ pub fn vec3_cross(a: &Vec3, b: &Vec3) -> Vec3 {
 }
 
 pub fn vec3_dot(a: &Vec3, b: &Vec3) -> f32 {
-    return (a.x * b.x) + (a.y * b.y) + (a.z * b.z);
+    (a.x * b.x) + (a.y * b.y) + (a.z * b.z)
 }
 
 pub fn vec3_normalize(v: &mut Vec3) {